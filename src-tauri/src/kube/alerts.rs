@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use chrono::Utc;
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, ListParams};
+use kube::runtime::watcher;
+use regex::{Regex, RegexBuilder};
+use tauri::Window;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::time::{interval, sleep, Duration, Instant};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::logs::LogOptions;
+use crate::kube::logtail::{Followed, LogEvent, LogLine};
+use crate::kube::Payload;
+use crate::store::alerts::AlertRule;
+use crate::utils::send_error;
+
+pub const ALERT_CHANNEL: &str = "app::alert";
+/// Matching lines sent with an alert, the latest ones.
+const MAX_SAMPLES: usize = 5;
+/// How often a background tail checks whether its rule was changed or removed.
+const CONTROL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, serde::Serialize)]
+pub struct Alert {
+    pub(crate) rule_id: i64,
+    pub(crate) rule: String,
+    pub(crate) context: String,
+    pub(crate) ns: String,
+    pub(crate) pattern: String,
+    /// Matches within the window when the alert fired.
+    pub(crate) count: usize,
+    pub(crate) threshold: u32,
+    pub(crate) window_minutes: u32,
+    pub(crate) fired_at: String,
+    pub(crate) samples: Vec<LogLine>,
+}
+
+/// Counts the matches of a rule over a sliding window. After firing, the rule stays quiet for
+/// one window, so a burst of errors raises one alert.
+struct AlertWindow {
+    pattern: Regex,
+    threshold: usize,
+    window: Duration,
+    hits: VecDeque<Instant>,
+    /// The latest matching lines still inside the window, at most `MAX_SAMPLES`.
+    samples: VecDeque<(Instant, LogLine)>,
+    quiet_until: Option<Instant>,
+}
+
+impl AlertWindow {
+    fn new(rule: &AlertRule) -> Result<Self, regex::Error> {
+        Ok(AlertWindow {
+            pattern: RegexBuilder::new(&rule.pattern).case_insensitive(rule.ignore_case).build()?,
+            threshold: rule.threshold as usize,
+            window: Duration::from_secs(u64::from(rule.window_minutes) * 60),
+            hits: VecDeque::new(),
+            samples: VecDeque::new(),
+            quiet_until: None,
+        })
+    }
+
+    /// Returns the match count and sample lines when the line takes the rule over its threshold.
+    /// `now` is when the line was read.
+    fn push(&mut self, line: LogLine, now: Instant) -> Option<(usize, Vec<LogLine>)> {
+        if self.quiet_until.map(|until| now < until).unwrap_or(false) || !self.pattern.is_match(&line.line) {
+            return None;
+        }
+        let window = self.window;
+        let expired = |at: &Instant| now.duration_since(*at) > window;
+        self.hits.push_back(now);
+        while self.hits.front().map(expired).unwrap_or(false) {
+            self.hits.pop_front();
+        }
+        while self.samples.front().map(|(at, _)| expired(at)).unwrap_or(false) {
+            self.samples.pop_front();
+        }
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, line));
+        if self.hits.len() <= self.threshold {
+            return None;
+        }
+        let count = self.hits.len();
+        self.hits.clear();
+        self.quiet_until = Some(now + self.window);
+        Some((count, self.samples.drain(..).map(|(_, line)| line).collect()))
+    }
+}
+
+impl KubeClientManager {
+    pub fn watch_log_alert(&self, window: Window, rule: AlertRule, rx: &Receiver<String>) {
+        let result = self._watch_log_alert(&window, &rule, rx);
+        if let Err(err) = result {
+            error!("Log alert {} stopped: {}", rule.name, err);
+            send_error(&window, &format!("Log alert {} stopped. Reason: {}", rule.name, err));
+        }
+    }
+
+    /// Follows the pods of the rule's namespace in the background, independent of the log view,
+    /// and emits an alert when the pattern matches too often. Only new lines are counted.
+    #[tokio::main]
+    async fn _watch_log_alert(&self, window: &Window, rule: &AlertRule, rx: &Receiver<String>) -> Result<(), Box<dyn Error>> {
+        let manager = self.for_context(&rule.context);
+        let client = match manager.init_client().await {
+            Some(client) => client,
+            None => {
+                send_error(window, &format!("Failed to start log alert {}. Reason Kubeclient failed.", rule.name));
+                return Ok(());
+            }
+        };
+        let mut counter = AlertWindow::new(rule)?;
+        let pods: Api<Pod> = manager.get_api(client, &rule.ns);
+        let params = match &rule.selector {
+            Some(selector) if !selector.trim().is_empty() => ListParams::default().labels(selector),
+            _ => ListParams::default(),
+        };
+        let mut events = watcher(pods.clone(), params).boxed();
+        let options = LogOptions {
+            tail_lines: Some(0),
+            ..LogOptions::default()
+        };
+        let (line_tx, mut line_rx) = unbounded_channel::<LogEvent>();
+        let mut followed = Followed::default();
+        let mut ticker = interval(CONTROL_INTERVAL);
+        info!("Watching logs in {} for alert {}", rule.ns, rule.name);
+
+        loop {
+            tokio::select! {
+                event = events.try_next() => {
+                    match event {
+                        Ok(Some(event)) => followed.on_event(&pods, &options, event, &line_tx),
+                        Ok(None) => break,
+                        Err(err) => {
+                            warn!("Pod watch for alert {} failed, retrying: {}", rule.name, err);
+                            sleep(Duration::from_secs(1)).await;
+                        },
+                    }
+                },
+                Some(event) = line_rx.recv() => {
+                    match event {
+                        LogEvent::Line(line) if line.marker.is_none() => {
+                            if let Some((count, samples)) = counter.push(line, Instant::now()) {
+                                _emit_alert(window, rule, count, samples);
+                            }
+                        },
+                        LogEvent::Line(_) => {},
+                        LogEvent::Ended { pod, container, .. } => followed.ended(&pod, &container),
+                    }
+                },
+                _ = ticker.tick() => {
+                    // Any message, or the task manager going away, ends the tail
+                    if !matches!(rx.try_recv(), Err(TryRecvError::Empty)) {
+                        break;
+                    }
+                }
+            }
+        }
+        followed.stop();
+        debug!("Stopped log alert {}", rule.name);
+        Ok(())
+    }
+}
+
+fn _emit_alert(window: &Window, rule: &AlertRule, count: usize, samples: Vec<LogLine>) {
+    info!("Log alert {} fired with {} matches", rule.name, count);
+    let alert = Alert {
+        rule_id: rule.id,
+        rule: rule.name.clone(),
+        context: rule.context.clone(),
+        ns: rule.ns.clone(),
+        pattern: rule.pattern.clone(),
+        count,
+        threshold: rule.threshold,
+        window_minutes: rule.window_minutes,
+        fired_at: Utc::now().to_rfc3339(),
+        samples,
+    };
+    let emitted = window.emit(
+        ALERT_CHANNEL,
+        Payload {
+            message: serde_json::to_string(&alert).unwrap(),
+            metadata: rule.id.to_string(),
+        },
+    );
+    if let Err(err) = emitted {
+        warn!("Failed to send alert {}: {}", rule.name, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert_window(threshold: u32) -> AlertWindow {
+        let rule = AlertRule {
+            name: "errors".to_string(),
+            ns: "default".to_string(),
+            pattern: "error".to_string(),
+            ignore_case: true,
+            threshold,
+            window_minutes: 1,
+            ..AlertRule::default()
+        };
+        AlertWindow::new(&rule).unwrap()
+    }
+
+    fn line(text: &str) -> LogLine {
+        LogLine { pod: "web-1".to_string(), line: text.to_string(), ..LogLine::default() }
+    }
+
+    fn at(start: Instant, seconds: u64) -> Instant {
+        start + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn fires_only_above_the_threshold() {
+        let start = Instant::now();
+        let mut counter = alert_window(2);
+        assert!(counter.push(line("ERROR one"), at(start, 0)).is_none());
+        assert!(counter.push(line("INFO ignored"), at(start, 1)).is_none());
+        assert!(counter.push(line("ERROR two"), at(start, 2)).is_none());
+        let (count, samples) = counter.push(line("ERROR three"), at(start, 3)).unwrap();
+        assert_eq!(count, 3);
+        let samples: Vec<&str> = samples.iter().map(|s| s.line.as_str()).collect();
+        assert_eq!(samples, vec!["ERROR one", "ERROR two", "ERROR three"]);
+    }
+
+    #[test]
+    fn forgets_matches_that_left_the_window() {
+        let start = Instant::now();
+        let mut counter = alert_window(2);
+        assert!(counter.push(line("error a"), at(start, 0)).is_none());
+        assert!(counter.push(line("error b"), at(start, 30)).is_none());
+        // At 61s the first match is more than a window old
+        assert!(counter.push(line("error c"), at(start, 61)).is_none());
+        let (count, samples) = counter.push(line("error d"), at(start, 90)).unwrap();
+        assert_eq!(count, 3);
+        let samples: Vec<&str> = samples.iter().map(|s| s.line.as_str()).collect();
+        assert_eq!(samples, vec!["error b", "error c", "error d"]);
+
+        // A match exactly one window old still counts
+        let mut counter = alert_window(1);
+        assert!(counter.push(line("error a"), at(start, 0)).is_none());
+        assert!(counter.push(line("error b"), at(start, 60)).is_some());
+    }
+
+    #[test]
+    fn stays_quiet_for_a_window_after_firing() {
+        let start = Instant::now();
+        let mut counter = alert_window(1);
+        assert!(counter.push(line("error"), at(start, 0)).is_none());
+        assert!(counter.push(line("error"), at(start, 1)).is_some());
+        for second in [2, 30, 60].iter() {
+            assert!(counter.push(line("error"), at(start, *second)).is_none(), "at {}s", second);
+        }
+        // Matches during the quiet period are not counted afterwards
+        assert!(counter.push(line("error"), at(start, 61)).is_none());
+        assert!(counter.push(line("error"), at(start, 62)).is_some());
+    }
+
+    #[test]
+    fn keeps_the_latest_samples() {
+        let start = Instant::now();
+        let mut counter = alert_window(10);
+        for n in 0..10 {
+            assert!(counter.push(line(&format!("error {}", n)), at(start, n)).is_none());
+        }
+        let (count, samples) = counter.push(line("error 10"), at(start, 10)).unwrap();
+        assert_eq!(count, 11);
+        let samples: Vec<&str> = samples.iter().map(|s| s.line.as_str()).collect();
+        assert_eq!(samples, vec!["error 6", "error 7", "error 8", "error 9", "error 10"]);
+    }
+}
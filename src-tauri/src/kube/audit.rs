@@ -0,0 +1,280 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+use kube::api::{Api, DynamicObject, Patch, PatchParams, PostParams, ResourceExt};
+use kube::core::GroupVersionKind;
+use kube::discovery::Scope;
+use kube::{Discovery, Resource};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::Window;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::store::audit::{self, AuditQuery, AuditRecord};
+use crate::utils::send_error;
+
+/// Verbs whose audit record carries a snapshot of everything the change touched. Rollbacks,
+/// pause and resume, cordon, suspend and image changes are recorded without one and stay
+/// out of this list.
+const UNDOABLE_VERBS: [&str; 5] = ["edit", "label", "annotate", "scale", "delete"];
+/// Server populated fields that must not be sent back when restoring an object.
+const SERVER_METADATA: [&str; 7] = [
+    "uid",
+    "resourceVersion",
+    "creationTimestamp",
+    "generation",
+    "managedFields",
+    "deletionTimestamp",
+    "selfLink",
+];
+
+/// A change about to be made to the cluster. The snapshots are taken by the caller so the
+/// record holds exactly what was sent and returned.
+pub(crate) struct Mutation {
+    record: AuditRecord,
+}
+
+impl Mutation {
+    pub(crate) fn new(verb: &str, api_version: &str, kind: &str, ns: &str, name: &str) -> Self {
+        Mutation {
+            record: AuditRecord {
+                api_version: api_version.to_string(),
+                kind: kind.to_string(),
+                ns: ns.to_string(),
+                name: name.to_string(),
+                verb: verb.to_string(),
+                ..AuditRecord::default()
+            },
+        }
+    }
+
+    pub(crate) fn typed<K: Resource<DynamicType = ()>>(verb: &str, ns: &str, name: &str) -> Self {
+        Mutation::new(verb, &K::api_version(&()), &K::kind(&()), ns, name)
+    }
+
+    pub(crate) fn for_object(verb: &str, obj: &DynamicObject, ns: &str) -> Self {
+        let (api_version, kind) = obj
+            .types
+            .as_ref()
+            .map(|t| (t.api_version.clone(), t.kind.clone()))
+            .unwrap_or_default();
+        let ns = obj.namespace().unwrap_or_else(|| ns.to_string());
+        Mutation::new(verb, &api_version, &kind, &ns, &obj.name_any())
+    }
+
+    pub(crate) fn before<T: Serialize>(mut self, obj: &T) -> Self {
+        self.record.before = _snapshot(obj);
+        self
+    }
+
+    fn undo_of(mut self, id: i64) -> Self {
+        self.record.undo_of = Some(id);
+        self
+    }
+}
+
+impl KubeClientManager {
+    /// Records the outcome of a mutation. A successful result that serializes to an object is
+    /// kept as the after state.
+    pub(crate) fn audit<T: Serialize, E: Display>(&self, mutation: Mutation, result: &Result<T, E>) {
+        let mut record = mutation.record;
+        record.ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+        record.context = self.context();
+        match result {
+            Ok(obj) => {
+                record.success = true;
+                record.after = _snapshot(obj);
+            },
+            Err(err) => {
+                record.success = false;
+                record.message = err.to_string();
+            }
+        }
+        if let Err(err) = audit::record(&record) {
+            warn!("Failed to record {} of {} {}: {}", record.verb, record.kind, record.name, err);
+        }
+    }
+
+    pub fn get_audit_log(&self, window: &Window, query: AuditQuery, cmd: &str) {
+        match audit::query(&query) {
+            Ok(mut records) => {
+                let context = self.context();
+                for record in records.iter_mut() {
+                    record.undoable = _check_undoable(record, &context).is_ok();
+                }
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&records).unwrap())
+            },
+            Err(err) => {
+                error!("Failed to query audit log: {}", err);
+                send_error(window, &format!("Failed to query audit log. Reason: {}", err));
+            }
+        }
+    }
+
+    pub fn undo_audit_record(&self, window: &Window, id: i64, cmd: &str) {
+        let result = self._undo_audit_record(window, id, cmd);
+        if let Err(err) = result {
+            error!("Failed to undo audit record {}: {}", id, err);
+            send_error(window, &format!("Failed to undo. Reason: {}", err));
+        }
+    }
+
+    /// Re-applies the state saved before a change. Label, annotation and scale changes are
+    /// reverted field by field, edits replace the object and deletes create it again.
+    #[tokio::main]
+    async fn _undo_audit_record(&self, window: &Window, id: i64, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let record = audit::get(id)?.ok_or_else(|| format!("Audit record {} not found", id))?;
+        _check_undoable(&record, &self.context())?;
+        if let Some(undo) = audit::undone_by(id)? {
+            return Err(format!("Change was already undone by record {}", undo).into());
+        }
+        let before: Value = serde_json::from_str(record.before.as_deref().ok_or("No prior state was recorded")?)?;
+
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let discovery = Discovery::new(client.clone()).run().await?;
+                let gvk = _gvk(&record.api_version, &record.kind);
+                let (ar, caps) = discovery
+                    .resolve_gvk(&gvk)
+                    .ok_or_else(|| format!("{} {} is not served by the cluster", record.api_version, record.kind))?;
+                let api: Api<DynamicObject> = if caps.scope == Scope::Cluster {
+                    Api::all_with(client.clone(), &ar)
+                } else {
+                    Api::namespaced_with(client.clone(), &record.ns, &ar)
+                };
+                let mut mutation = Mutation::new("undo", &record.api_version, &record.kind, &record.ns, &record.name).undo_of(id);
+                let result = match record.verb.as_str() {
+                    "delete" => {
+                        let prior: DynamicObject = serde_json::from_value(_restorable(before))?;
+                        if let Some(owner) = prior.owner_references().first() {
+                            return Err(format!(
+                                "{} was owned by {} {}. Its controller manages it, so it cannot be restored",
+                                record.name, owner.kind, owner.name
+                            )
+                            .into());
+                        }
+                        api.create(&PostParams::default(), &prior).await
+                    },
+                    "edit" => {
+                        let current = api.get(&record.name).await?;
+                        let mut prior: DynamicObject = serde_json::from_value(_restorable(before))?;
+                        prior.metadata.resource_version = current.resource_version();
+                        mutation = mutation.before(&current);
+                        api.replace(&record.name, &PostParams::default(), &prior).await
+                    },
+                    verb => {
+                        let current = api.get(&record.name).await?;
+                        let after: Value = match record.after.as_deref() {
+                            Some(after) => serde_json::from_str(after)?,
+                            None => Value::Null,
+                        };
+                        mutation = mutation.before(&current);
+                        let patch = _revert_patch(verb, &before, &after);
+                        api.patch(&record.name, &PatchParams::default(), &Patch::Merge(patch)).await
+                    }
+                };
+                self.audit(mutation, &result);
+                _finish_undo(window, cmd, &record, result)
+            },
+            None => {
+                send_error(window, "Failed to undo. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn _check_undoable(record: &AuditRecord, context: &str) -> Result<(), String> {
+    if !UNDOABLE_VERBS.contains(&record.verb.as_str()) {
+        return Err(format!("'{}' changes cannot be undone", record.verb));
+    }
+    if !record.success {
+        return Err("Only successful changes can be undone".to_string());
+    }
+    if record.before.is_none() {
+        return Err("No prior state was recorded".to_string());
+    }
+    if record.context != context {
+        return Err(format!("Change was made in context {}. Switch to it to undo", record.context));
+    }
+    Ok(())
+}
+
+fn _finish_undo(
+    window: &Window,
+    cmd: &str,
+    record: &AuditRecord,
+    result: Result<DynamicObject, kube::Error>,
+) -> Result<(), Box<dyn Error>> {
+    let restored = result?;
+    info!("Undid {} of {} {}", record.verb, record.kind, record.name);
+    dispatch_to_frontend(window, cmd, serde_json::to_string(&restored).unwrap());
+    Ok(())
+}
+
+/// Builds a merge patch that puts back the fields a label, annotation or scale change touched.
+/// Keys added by the change are removed with `null`. Workload changes made with
+/// `include_template` also touched the pod template, so it is reverted the same way.
+fn _revert_patch(verb: &str, before: &Value, after: &Value) -> Value {
+    match verb {
+        "scale" => json!({ "spec": { "replicas": before["spec"]["replicas"] } }),
+        _ => {
+            let field = if verb == "label" { "labels" } else { "annotations" };
+            let mut patch = json!({ "metadata": { field: _revert_keys(&before["metadata"][field], &after["metadata"][field]) } });
+            let template_before = &before["spec"]["template"]["metadata"][field];
+            let template_after = &after["spec"]["template"]["metadata"][field];
+            if template_before != template_after {
+                patch["spec"] = json!({ "template": { "metadata": { field: _revert_keys(template_before, template_after) } } });
+            }
+            patch
+        }
+    }
+}
+
+fn _revert_keys(before: &Value, after: &Value) -> Map<String, Value> {
+    let mut changes: Map<String, Value> = Map::new();
+    if let Some(added) = after.as_object() {
+        for key in added.keys() {
+            changes.insert(key.clone(), Value::Null);
+        }
+    }
+    if let Some(prior) = before.as_object() {
+        for (key, value) in prior {
+            changes.insert(key.clone(), value.clone());
+        }
+    }
+    changes
+}
+
+/// Strips server populated fields and status so the saved object can be sent back.
+fn _restorable(mut obj: Value) -> Value {
+    if let Some(metadata) = obj["metadata"].as_object_mut() {
+        for field in SERVER_METADATA.iter() {
+            metadata.remove(*field);
+        }
+    }
+    if let Some(obj) = obj.as_object_mut() {
+        obj.remove("status");
+    }
+    obj
+}
+
+fn _snapshot<T: Serialize>(obj: &T) -> Option<String> {
+    let mut value = serde_json::to_value(obj).ok()?;
+    if !value.is_object() {
+        return None;
+    }
+    if let Some(metadata) = value["metadata"].as_object_mut() {
+        metadata.remove("managedFields");
+    }
+    Some(value.to_string())
+}
+
+fn _gvk(api_version: &str, kind: &str) -> GroupVersionKind {
+    match api_version.split_once('/') {
+        Some((group, version)) => GroupVersionKind::gvk(group, version, kind),
+        None => GroupVersionKind::gvk("", api_version, kind),
+    }
+}
+
@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use kube::api::{Api, ListParams, Patch, PatchParams, ResourceExt};
+use kube::discovery::Scope;
+use kube::{Client, Discovery};
+use tauri::Window;
+use crate::kube::audit::Mutation;
+use crate::kube::common::{api_for_kind, dispatch_to_frontend};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::kubectl::resolve_api_resource;
+use crate::kube::models::DeleteOptions;
+use crate::kube::labels::{metadata_patch, validate_changes, MetadataField};
+use crate::kube::rollout::WorkloadKind;
+use crate::utils::send_error;
+
+const BULK_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct ResourceRef {
+    pub(crate) kind: String,
+    #[serde(default)]
+    pub(crate) ns: String,
+    pub(crate) name: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum BulkVerb {
+    Delete,
+    Restart,
+    Scale,
+    Label,
+    Annotate,
+}
+
+impl BulkVerb {
+    pub(crate) fn from_command(command: &str) -> Option<BulkVerb> {
+        match command {
+            "bulk_delete" => Some(BulkVerb::Delete),
+            "bulk_restart" => Some(BulkVerb::Restart),
+            "bulk_scale" => Some(BulkVerb::Scale),
+            "bulk_label" => Some(BulkVerb::Label),
+            "bulk_annotate" => Some(BulkVerb::Annotate),
+            _ => None,
+        }
+    }
+
+    /// Verbs that remove objects or replace running pods need an explicit confirmation count.
+    fn is_destructive(&self) -> bool {
+        matches!(self, BulkVerb::Delete | BulkVerb::Restart | BulkVerb::Scale)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BulkRequest {
+    pub(crate) items: Vec<ResourceRef>,
+    pub(crate) kind: Option<String>,
+    pub(crate) ns: String,
+    pub(crate) selector: Option<String>,
+    pub(crate) replicas: Option<i32>,
+    pub(crate) changes: BTreeMap<String, Option<String>>,
+    pub(crate) confirm: Option<usize>,
+    pub(crate) delete_options: DeleteOptions,
+}
+
+impl BulkRequest {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let items = match args.get("items") {
+            Some(items) => serde_json::from_str(items).map_err(|e| format!("Invalid items: {}", e))?,
+            None => Vec::new(),
+        };
+        let changes = match args.get("changes") {
+            Some(changes) => serde_json::from_str(changes).map_err(|e| format!("Invalid changes: {}", e))?,
+            None => BTreeMap::new(),
+        };
+        Ok(BulkRequest {
+            items,
+            kind: args.get("kind").cloned(),
+            ns: args.get("ns").cloned().unwrap_or_default(),
+            selector: args.get("selector").cloned().filter(|s| !s.trim().is_empty()),
+            replicas: args.get("replicas").and_then(|r| r.parse().ok()),
+            changes,
+            confirm: args.get("confirm").and_then(|c| c.parse().ok()),
+            delete_options: DeleteOptions::from_args(args)?,
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct BulkItemResult {
+    pub(crate) kind: String,
+    pub(crate) ns: String,
+    pub(crate) name: String,
+    pub(crate) success: bool,
+    pub(crate) message: String,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct BulkResult {
+    pub(crate) verb: String,
+    pub(crate) total: usize,
+    pub(crate) succeeded: usize,
+    pub(crate) failed: usize,
+    pub(crate) confirmation_required: Option<usize>,
+    pub(crate) items: Vec<BulkItemResult>,
+}
+
+impl KubeClientManager {
+    pub fn bulk_operation(&self, window: &Window, verb: BulkVerb, request: BulkRequest, cmd: &str) {
+        let result = self._bulk_operation(window, verb, &request, cmd);
+        if let Err(err) = result {
+            error!("Failed bulk {:?}: {}", verb, err);
+            send_error(window, &format!("Failed bulk operation. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _bulk_operation(
+        &self,
+        window: &Window,
+        verb: BulkVerb,
+        request: &BulkRequest,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                _validate_request(verb, request)?;
+                let discovery = Discovery::new(client.clone()).run().await?;
+                let targets = self.resolve_targets(client.clone(), &discovery, request).await?;
+                let mut result = BulkResult {
+                    verb: format!("{:?}", verb).to_lowercase(),
+                    total: targets.len(),
+                    ..BulkResult::default()
+                };
+                if verb.is_destructive() && request.confirm != Some(targets.len()) {
+                    result.confirmation_required = Some(targets.len());
+                    dispatch_to_frontend(window, cmd, serde_json::to_string(&result).unwrap());
+                    return Ok(());
+                }
+
+                result.items = futures::stream::iter(targets)
+                    .map(|target| self.bulk_apply(client.clone(), &discovery, verb, target, request))
+                    .buffer_unordered(BULK_CONCURRENCY)
+                    .collect()
+                    .await;
+                result.succeeded = result.items.iter().filter(|i| i.success).count();
+                result.failed = result.total - result.succeeded;
+                info!("Bulk {:?} finished: {} succeeded, {} failed", verb, result.succeeded, result.failed);
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&result).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed bulk operation. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    async fn resolve_targets(
+        &self,
+        client: Client,
+        discovery: &Discovery,
+        request: &BulkRequest,
+    ) -> Result<Vec<ResourceRef>, Box<dyn Error>> {
+        let mut targets = request.items.clone();
+        if let Some(selector) = &request.selector {
+            let kind = request.kind.clone().ok_or("A kind is required with a selector")?;
+            let (api, ar, _caps) = api_for_kind(client, discovery, &kind, &request.ns)?;
+            let lp = ListParams::default().labels(selector);
+            for obj in api.list(&lp).await? {
+                targets.push(ResourceRef {
+                    kind: ar.kind.clone(),
+                    ns: obj.namespace().unwrap_or_default(),
+                    name: obj.name_any(),
+                });
+            }
+        }
+        // An object can be listed twice, or listed and matched by the selector, and must only be
+        // acted on once. Kinds are compared as resolved, so `deploy` and `Deployment` are the same.
+        let mut seen = HashSet::new();
+        targets.retain(|target| {
+            let key = match resolve_api_resource(discovery, target.kind.trim()) {
+                Some((ar, caps)) if caps.scope == Scope::Cluster => (ar.kind, String::new(), target.name.clone()),
+                Some((ar, _caps)) => (ar.kind, target.ns.clone(), target.name.clone()),
+                None => (target.kind.clone(), target.ns.clone(), target.name.clone()),
+            };
+            seen.insert(key)
+        });
+        Ok(targets)
+    }
+
+    async fn bulk_apply(
+        &self,
+        client: Client,
+        discovery: &Discovery,
+        verb: BulkVerb,
+        target: ResourceRef,
+        request: &BulkRequest,
+    ) -> BulkItemResult {
+        let outcome = self.bulk_apply_one(client, discovery, verb, &target, request).await;
+        let (success, message) = match outcome {
+            Ok(message) => (true, message),
+            Err(err) => (false, err.to_string()),
+        };
+        BulkItemResult {
+            kind: target.kind,
+            ns: target.ns,
+            name: target.name,
+            success,
+            message,
+        }
+    }
+
+    async fn bulk_apply_one(
+        &self,
+        client: Client,
+        discovery: &Discovery,
+        verb: BulkVerb,
+        target: &ResourceRef,
+        request: &BulkRequest,
+    ) -> Result<String, Box<dyn Error>> {
+        match verb {
+            BulkVerb::Delete => {
+                let (api, ar, _caps) = api_for_kind(client, discovery, &target.kind, &target.ns)?;
+                let mut mutation = Mutation::new("delete", &ar.api_version, &ar.kind, &target.ns, &target.name);
+                if let Ok(before) = api.get(&target.name).await {
+                    mutation = mutation.before(&before);
+                }
+                let result = api.delete(&target.name, &request.delete_options.params()).await;
+                self.audit(mutation, &result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+                result?;
+                Ok("Deleted".to_string())
+            },
+            BulkVerb::Restart => {
+                match WorkloadKind::from_kind(&target.kind) {
+                    Some(WorkloadKind::Deployment) => {
+                        let api: Api<Deployment> = self.get_api(client, &target.ns);
+                        let result = api.restart(&target.name).await;
+                        self.audit(Mutation::typed::<Deployment>("restart", &target.ns, &target.name), &result);
+                        result?;
+                    },
+                    Some(WorkloadKind::StatefulSet) => {
+                        let api: Api<StatefulSet> = self.get_api(client, &target.ns);
+                        let result = api.restart(&target.name).await;
+                        self.audit(Mutation::typed::<StatefulSet>("restart", &target.ns, &target.name), &result);
+                        result?;
+                    },
+                    Some(WorkloadKind::DaemonSet) => {
+                        let api: Api<DaemonSet> = self.get_api(client, &target.ns);
+                        let result = api.restart(&target.name).await;
+                        self.audit(Mutation::typed::<DaemonSet>("restart", &target.ns, &target.name), &result);
+                        result?;
+                    },
+                    None => return Err(format!("Restart is not supported for {}", target.kind).into()),
+                }
+                Ok("Restarted".to_string())
+            },
+            BulkVerb::Scale => {
+                let workload = WorkloadKind::from_kind(&target.kind)
+                    .ok_or_else(|| format!("Scale is not supported for {}", target.kind))?;
+                let replicas = request.replicas.unwrap_or_default();
+                self.scale(client, &target.ns, workload, &target.name, replicas).await?;
+                Ok(format!("Scaled to {}", replicas))
+            },
+            BulkVerb::Label | BulkVerb::Annotate => {
+                let field = _metadata_field(verb);
+                let (api, ar, _caps) = api_for_kind(client, discovery, &target.kind, &target.ns)?;
+                let patch = metadata_patch(field, &request.changes, false);
+                let before = api.get(&target.name).await?;
+                let result = api.patch(&target.name, &PatchParams::default(), &Patch::Merge(patch)).await;
+                let mutation = Mutation::new(field.verb(), &ar.api_version, &ar.kind, &target.ns, &target.name);
+                self.audit(mutation.before(&before), &result);
+                result?;
+                Ok(format!("Updated {}", field.key()))
+            }
+        }
+    }
+}
+
+fn _validate_request(verb: BulkVerb, request: &BulkRequest) -> Result<(), Box<dyn Error>> {
+    if request.items.is_empty() && request.selector.is_none() {
+        return Err("Select at least one resource or provide a selector".into());
+    }
+    match verb {
+        BulkVerb::Scale if request.replicas.is_none() => Err("Replicas are required to scale".into()),
+        BulkVerb::Label | BulkVerb::Annotate => {
+            validate_changes(_metadata_field(verb), &request.changes)?;
+            Ok(())
+        },
+        _ => Ok(()),
+    }
+}
+
+fn _metadata_field(verb: BulkVerb) -> MetadataField {
+    if verb == BulkVerb::Label {
+        MetadataField::Labels
+    } else {
+        MetadataField::Annotations
+    }
+}
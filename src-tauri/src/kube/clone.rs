@@ -0,0 +1,527 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::{Api, DynamicObject, Patch, PatchParams, PostParams, ResourceExt};
+use kube::core::GroupVersionKind;
+use kube::discovery::{ApiResource, Scope};
+use kube::{Client, Discovery};
+use serde_json::{json, Value};
+use tauri::Window;
+use crate::kube::audit::Mutation;
+use crate::kube::bulk::ResourceRef;
+use crate::kube::common::{api_for_kind, dispatch_to_frontend};
+use crate::kube::export::{export_value, namespaced_objects, parse_kinds};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::protection;
+use crate::utils::send_error;
+
+const MAX_RENAME_ATTEMPTS: usize = 20;
+/// Objects are applied in this order so that a workload finds its config and storage in place.
+const APPLY_ORDER: [&str; 7] = [
+    "ServiceAccount",
+    "Secret",
+    "ConfigMap",
+    "PersistentVolumeClaim",
+    "Role",
+    "RoleBinding",
+    "Service",
+];
+/// Labels the Job controller adds. They hold the uid of the source Job and would not match a copy.
+const JOB_CONTROLLER_LABELS: [&str; 4] = [
+    "controller-uid",
+    "job-name",
+    "batch.kubernetes.io/controller-uid",
+    "batch.kubernetes.io/job-name",
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Clone, Debug)]
+pub struct CloneRequest {
+    /// Empty means the current context.
+    pub(crate) source_context: String,
+    pub(crate) source_ns: String,
+    pub(crate) target_context: String,
+    pub(crate) target_ns: String,
+    pub(crate) items: Vec<ResourceRef>,
+    pub(crate) kinds: Vec<String>,
+    pub(crate) name_prefix: String,
+    pub(crate) conflict: ConflictPolicy,
+    pub(crate) dry_run: bool,
+}
+
+impl CloneRequest {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let text = |key: &str| args.get(key).map(|v| v.trim().to_string()).unwrap_or_default();
+        let items: Vec<ResourceRef> = match args.get("items") {
+            Some(items) => serde_json::from_str(items).map_err(|e| format!("Invalid items: {}", e))?,
+            None => Vec::new(),
+        };
+        let kinds = parse_kinds(args.get("kinds"))?;
+        if items.is_empty() && kinds.is_empty() {
+            return Err("Select resources or kinds to clone".to_string());
+        }
+        let conflict = match args.get("conflict").map(|c| c.as_str()) {
+            None | Some("skip") => ConflictPolicy::Skip,
+            Some("overwrite") => ConflictPolicy::Overwrite,
+            Some("rename") => ConflictPolicy::Rename,
+            Some(other) => return Err(format!("Unknown conflict policy {}", other)),
+        };
+        let request = CloneRequest {
+            source_context: text("source_context"),
+            source_ns: text("source_ns"),
+            target_context: text("target_context"),
+            target_ns: text("target_ns"),
+            items,
+            kinds,
+            name_prefix: text("name_prefix"),
+            conflict,
+            dry_run: protection::is_dry_run(args),
+        };
+        if request.source_ns.is_empty() || request.target_ns.is_empty() {
+            return Err("Source and target namespaces are required".to_string());
+        }
+        if request.source_context == request.target_context
+            && request.source_ns == request.target_ns
+            && request.name_prefix.is_empty()
+            && request.conflict != ConflictPolicy::Rename
+        {
+            return Err("Source and target are the same. Set a name prefix or the rename policy".to_string());
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct CloneItemResult {
+    pub(crate) kind: String,
+    pub(crate) source_name: String,
+    pub(crate) target_name: String,
+    /// One of create, overwrite, rename or skip.
+    pub(crate) action: String,
+    pub(crate) success: bool,
+    pub(crate) message: String,
+}
+
+impl CloneItemResult {
+    /// An object that could not be read or planned, so nothing was sent to the target.
+    fn failed(kind: &str, source_name: &str, message: String) -> Self {
+        CloneItemResult {
+            kind: kind.to_string(),
+            source_name: source_name.to_string(),
+            action: "skip".to_string(),
+            message,
+            ..CloneItemResult::default()
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct CloneReport {
+    pub(crate) source_context: String,
+    pub(crate) source_ns: String,
+    pub(crate) target_context: String,
+    pub(crate) target_ns: String,
+    pub(crate) dry_run: bool,
+    pub(crate) total: usize,
+    pub(crate) succeeded: usize,
+    pub(crate) skipped: usize,
+    pub(crate) failed: usize,
+    pub(crate) items: Vec<CloneItemResult>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// An object read from the source, and what will be done with it in the target.
+struct ClonePlan {
+    ar: ApiResource,
+    source_name: String,
+    target_name: String,
+    action: &'static str,
+    object: Value,
+}
+
+impl KubeClientManager {
+    pub fn clone_resources(&self, window: &Window, request: CloneRequest, cmd: &str) {
+        let result = self._clone_resources(window, &request, cmd);
+        if let Err(err) = result {
+            error!("Failed to clone resources from {}: {}", request.source_ns, err);
+            send_error(window, &format!("Failed to clone resources. Reason: {}", err));
+        }
+    }
+
+    /// Reads the objects from the source, plans a name for each one in the target, rewrites
+    /// references to renamed objects and then applies them. A dry run sends everything to the
+    /// target with server side dry run, so the report shows what the API server would accept.
+    #[tokio::main]
+    async fn _clone_resources(&self, window: &Window, request: &CloneRequest, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let source = self._manager_for(&request.source_context);
+        let target = self._manager_for(&request.target_context);
+        let (source_client, target_client) = match (source.init_client().await, target.init_client().await) {
+            (Some(source_client), Some(target_client)) => (source_client, target_client),
+            _ => {
+                send_error(window, "Failed to clone resources. Reason Kubeclient failed.");
+                return Ok(());
+            }
+        };
+        let mut report = CloneReport {
+            source_context: source.context(),
+            source_ns: request.source_ns.clone(),
+            target_context: target.context(),
+            target_ns: request.target_ns.clone(),
+            dry_run: request.dry_run,
+            ..CloneReport::default()
+        };
+        let source_discovery = Discovery::new(source_client.clone()).run().await?;
+        let target_discovery = Discovery::new(target_client.clone()).run().await?;
+
+        let objects = _read_sources(source_client, &source_discovery, request, &mut report).await;
+        let mut plans = Vec::new();
+        for (ar, obj) in objects {
+            match _plan(target_client.clone(), &target_discovery, request, &ar, &obj).await {
+                Ok(plan) => plans.push(plan),
+                Err(err) => report.items.push(CloneItemResult::failed(&ar.kind, &obj.name_any(), err.to_string())),
+            }
+        }
+        let renames: HashMap<(String, String), String> = plans
+            .iter()
+            .filter(|p| p.source_name != p.target_name)
+            .map(|p| ((p.ar.kind.clone(), p.source_name.clone()), p.target_name.clone()))
+            .collect();
+        for plan in plans.iter_mut() {
+            _rewrite_references(&mut plan.object, &renames);
+        }
+        plans.sort_by_key(|p| APPLY_ORDER.iter().position(|k| *k == p.ar.kind).unwrap_or(APPLY_ORDER.len()));
+
+        let mut namespace_pending = false;
+        if !plans.iter().all(|p| p.action == "skip") {
+            match target.ensure_namespace(target_client.clone(), &request.target_ns, request.dry_run).await {
+                Ok(true) if request.dry_run => {
+                    namespace_pending = true;
+                    report.warnings.push(format!("Namespace {} does not exist and would be created", request.target_ns));
+                },
+                Ok(_) => {},
+                Err(err) => report.warnings.push(format!("Could not create namespace {}: {}", request.target_ns, err)),
+            }
+        }
+        for plan in plans {
+            let item = target
+                .apply_clone(target_client.clone(), &target_discovery, request, plan, namespace_pending)
+                .await;
+            report.items.push(item);
+        }
+        report.total = report.items.len();
+        report.skipped = report.items.iter().filter(|i| i.success && i.action == "skip").count();
+        report.succeeded = report.items.iter().filter(|i| i.success && i.action != "skip").count();
+        report.failed = report.total - report.succeeded - report.skipped;
+        info!(
+            "Cloned {} of {} objects from {}/{} to {}/{}{}",
+            report.succeeded,
+            report.total,
+            report.source_context,
+            report.source_ns,
+            report.target_context,
+            report.target_ns,
+            if request.dry_run { " (dry run)" } else { "" }
+        );
+        dispatch_to_frontend(window, cmd, serde_json::to_string(&report).unwrap());
+        Ok(())
+    }
+
+    fn _manager_for(&self, context: &str) -> KubeClientManager {
+        if context.is_empty() {
+            self.clone()
+        } else {
+            self.for_context(context)
+        }
+    }
+
+    /// Creates the target namespace when it is missing and returns whether it was missing. A dry run
+    /// leaves it missing, because the server rejects dry run objects for a namespace that does not exist.
+    async fn ensure_namespace(&self, client: Client, ns: &str, dry_run: bool) -> Result<bool, kube::Error> {
+        let api: Api<Namespace> = Api::all(client);
+        if api.get_opt(ns).await?.is_some() {
+            return Ok(false);
+        }
+        if dry_run {
+            return Ok(true);
+        }
+        let namespace: Namespace = serde_json::from_value(json!({ "metadata": { "name": ns } })).unwrap();
+        let result = api.create(&PostParams::default(), &namespace).await;
+        self.audit(Mutation::typed::<Namespace>("create", "", ns), &result);
+        result.map(|_| true)
+    }
+
+    async fn apply_clone(
+        &self,
+        client: Client,
+        discovery: &Discovery,
+        request: &CloneRequest,
+        plan: ClonePlan,
+        namespace_pending: bool,
+    ) -> CloneItemResult {
+        let ClonePlan { ar, source_name, target_name, action, object } = plan;
+        let mut item = CloneItemResult {
+            kind: ar.kind.clone(),
+            source_name,
+            target_name: target_name.clone(),
+            action: action.to_string(),
+            ..CloneItemResult::default()
+        };
+        if action == "skip" {
+            item.success = true;
+            item.message = "Already exists in the target".to_string();
+            return item;
+        }
+        if namespace_pending {
+            item.success = true;
+            item.message = format!("Not sent for dry run, namespace {} would be created first", request.target_ns);
+            return item;
+        }
+        let outcome = async {
+            let api = _target_api(client, discovery, &ar, &request.target_ns)?;
+            let obj: DynamicObject = serde_json::from_value(object)?;
+            let result = if action == "overwrite" {
+                let mut params = PatchParams::apply("yaki").force();
+                params.dry_run = request.dry_run;
+                let mut mutation = Mutation::for_object("edit", &obj, &request.target_ns);
+                if let Ok(before) = api.get(&target_name).await {
+                    mutation = mutation.before(&before);
+                }
+                let result = api.patch(&target_name, &params, &Patch::Apply(&obj)).await;
+                if !request.dry_run {
+                    self.audit(mutation, &result);
+                }
+                result
+            } else {
+                let params = PostParams { dry_run: request.dry_run, ..PostParams::default() };
+                let result = api.create(&params, &obj).await;
+                if !request.dry_run {
+                    self.audit(Mutation::for_object("create", &obj, &request.target_ns), &result);
+                }
+                result
+            };
+            result.map_err(|e| Box::new(e) as Box<dyn Error>)
+        }
+        .await;
+        match outcome {
+            Ok(_) => {
+                item.success = true;
+                item.message = match (request.dry_run, action) {
+                    (true, _) => "Accepted by server dry run".to_string(),
+                    (false, "overwrite") => "Overwritten".to_string(),
+                    (false, "rename") => format!("Created as {}", target_name),
+                    _ => "Created".to_string(),
+                };
+            },
+            Err(err) => item.message = err.to_string(),
+        }
+        item
+    }
+}
+
+/// Reads the selected objects and the objects of the selected kinds. An object that cannot be
+/// read is reported as failed and the rest are still cloned.
+async fn _read_sources(
+    client: Client,
+    discovery: &Discovery,
+    request: &CloneRequest,
+    report: &mut CloneReport,
+) -> Vec<(ApiResource, DynamicObject)> {
+    let mut objects = Vec::new();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    for item in &request.items {
+        let (api, ar, caps) = match api_for_kind(client.clone(), discovery, &item.kind, &request.source_ns) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                report.items.push(CloneItemResult::failed(&item.kind, &item.name, err));
+                continue;
+            }
+        };
+        if caps.scope == Scope::Cluster {
+            report.warnings.push(format!("{} {} is cluster scoped and was not cloned", ar.kind, item.name));
+            continue;
+        }
+        let obj = match api.get(&item.name).await {
+            Ok(obj) => obj,
+            Err(err) => {
+                report.items.push(CloneItemResult::failed(&ar.kind, &item.name, err.to_string()));
+                continue;
+            }
+        };
+        if seen.insert((ar.kind.clone(), obj.name_any())) {
+            objects.push((ar, obj));
+        }
+    }
+    if !request.kinds.is_empty() {
+        let warnings = &mut report.warnings;
+        for (ar, obj) in namespaced_objects(client, discovery, &request.source_ns, &request.kinds, warnings).await {
+            // Objects with a controller are created again by their owner in the target
+            if obj.owner_references().iter().any(|o| o.controller == Some(true)) {
+                continue;
+            }
+            if seen.insert((ar.kind.clone(), obj.name_any())) {
+                objects.push((ar, obj));
+            }
+        }
+    }
+    objects
+}
+
+/// Cleans an object for the target and decides its name there according to the conflict policy.
+async fn _plan(
+    client: Client,
+    discovery: &Discovery,
+    request: &CloneRequest,
+    ar: &ApiResource,
+    obj: &DynamicObject,
+) -> Result<ClonePlan, Box<dyn Error>> {
+    let source_name = obj.name_any();
+    if ar.kind == "Secret" && obj.data["type"].as_str() == Some("kubernetes.io/service-account-token") {
+        return Err("Service account tokens are issued by the target cluster".into());
+    }
+    let mut object = export_value(ar, obj);
+    _strip_cluster_fields(&mut object, &ar.kind);
+
+    let api = _target_api(client, discovery, ar, &request.target_ns)?;
+    let mut target_name = format!("{}{}", request.name_prefix, source_name);
+    let mut action = "create";
+    if api.get_opt(&target_name).await?.is_some() {
+        match request.conflict {
+            ConflictPolicy::Skip => action = "skip",
+            ConflictPolicy::Overwrite => action = "overwrite",
+            ConflictPolicy::Rename => {
+                action = "rename";
+                target_name = _free_name(&api, &target_name).await?;
+            }
+        }
+    }
+    object["metadata"]["name"] = Value::String(target_name.clone());
+    object["metadata"]["namespace"] = Value::String(request.target_ns.clone());
+    Ok(ClonePlan {
+        ar: ar.clone(),
+        source_name,
+        target_name,
+        action,
+        object,
+    })
+}
+
+/// The same group, version and kind in the target cluster.
+fn _target_api(client: Client, discovery: &Discovery, ar: &ApiResource, ns: &str) -> Result<Api<DynamicObject>, String> {
+    let gvk = GroupVersionKind::gvk(&ar.group, &ar.version, &ar.kind);
+    let (target_ar, _caps) = discovery
+        .resolve_gvk(&gvk)
+        .ok_or_else(|| format!("{} {} is not served by the target cluster", ar.api_version, ar.kind))?;
+    Ok(Api::namespaced_with(client, ns, &target_ar))
+}
+
+async fn _free_name(api: &Api<DynamicObject>, name: &str) -> Result<String, Box<dyn Error>> {
+    for attempt in 1..=MAX_RENAME_ATTEMPTS {
+        let candidate = if attempt == 1 {
+            format!("{}-copy", name)
+        } else {
+            format!("{}-copy-{}", name, attempt)
+        };
+        if api.get_opt(&candidate).await?.is_none() {
+            return Ok(candidate);
+        }
+    }
+    Err(format!("No free name found for {}", name).into())
+}
+
+/// Removes fields that tie an object to the cluster it was read from: owners, bound volumes
+/// and the selectors the Job controller generated.
+fn _strip_cluster_fields(object: &mut Value, kind: &str) {
+    if let Some(metadata) = object.pointer_mut("/metadata").and_then(|m| m.as_object_mut()) {
+        metadata.remove("ownerReferences");
+    }
+    match kind {
+        "PersistentVolumeClaim" => {
+            if let Some(spec) = object.pointer_mut("/spec").and_then(|s| s.as_object_mut()) {
+                spec.remove("volumeName");
+            }
+            if let Some(annotations) = object.pointer_mut("/metadata/annotations").and_then(|a| a.as_object_mut()) {
+                annotations.retain(|key, _| !key.starts_with("pv.kubernetes.io/") && !key.starts_with("volume.kubernetes.io/"));
+            }
+        },
+        "Job" => {
+            if let Some(spec) = object.pointer_mut("/spec").and_then(|s| s.as_object_mut()) {
+                spec.remove("selector");
+            }
+            if let Some(labels) = object.pointer_mut("/spec/template/metadata/labels").and_then(|l| l.as_object_mut()) {
+                for label in JOB_CONTROLLER_LABELS.iter() {
+                    labels.remove(*label);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Points a cloned workload at the renamed copies of the ConfigMaps, Secrets, claims and
+/// service accounts it uses. Only fields that are present are touched.
+fn _rewrite_references(object: &mut Value, renames: &HashMap<(String, String), String>) {
+    if renames.is_empty() {
+        return;
+    }
+    let pointer = match object["kind"].as_str() {
+        Some("Pod") => "/spec",
+        Some("CronJob") => "/spec/jobTemplate/spec/template/spec",
+        _ => "/spec/template/spec",
+    };
+    let pod_spec = match object.pointer_mut(pointer) {
+        Some(pod_spec) if pod_spec.is_object() => pod_spec,
+        _ => return,
+    };
+    let rename = |value: &mut Value, pointer: &str, kind: &str| {
+        if let Some(field) = value.pointer_mut(pointer) {
+            let new_name = field.as_str().and_then(|name| renames.get(&(kind.to_string(), name.to_string())));
+            if let Some(new_name) = new_name {
+                *field = Value::String(new_name.clone());
+            }
+        }
+    };
+    rename(pod_spec, "/serviceAccountName", "ServiceAccount");
+    if let Some(secrets) = pod_spec.pointer_mut("/imagePullSecrets").and_then(|s| s.as_array_mut()) {
+        for secret in secrets {
+            rename(secret, "/name", "Secret");
+        }
+    }
+    if let Some(volumes) = pod_spec.pointer_mut("/volumes").and_then(|v| v.as_array_mut()) {
+        for volume in volumes {
+            rename(volume, "/configMap/name", "ConfigMap");
+            rename(volume, "/secret/secretName", "Secret");
+            rename(volume, "/persistentVolumeClaim/claimName", "PersistentVolumeClaim");
+            if let Some(sources) = volume.pointer_mut("/projected/sources").and_then(|s| s.as_array_mut()) {
+                for source in sources {
+                    rename(source, "/configMap/name", "ConfigMap");
+                    rename(source, "/secret/name", "Secret");
+                }
+            }
+        }
+    }
+    for containers in ["/initContainers", "/containers"].iter() {
+        if let Some(containers) = pod_spec.pointer_mut(containers).and_then(|c| c.as_array_mut()) {
+            for container in containers {
+                if let Some(env_from) = container.pointer_mut("/envFrom").and_then(|e| e.as_array_mut()) {
+                    for source in env_from {
+                        rename(source, "/configMapRef/name", "ConfigMap");
+                        rename(source, "/secretRef/name", "Secret");
+                    }
+                }
+                if let Some(env) = container.pointer_mut("/env").and_then(|e| e.as_array_mut()) {
+                    for var in env {
+                        rename(var, "/valueFrom/configMapKeyRef/name", "ConfigMap");
+                        rename(var, "/valueFrom/secretKeyRef/name", "Secret");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use crate::kube::kubectl::resolve_api_resource;
 use crate::kube::models::CommandResult;
+use std::collections::BTreeMap;
 use futures::TryFutureExt;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::api::{Api, DynamicObject};
 use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::discovery::{ApiCapabilities, ApiResource, Discovery, Scope};
 use kube::{Client, Config, Error};
 use tauri::Window;
 
@@ -31,3 +36,70 @@ pub async fn init_client(cluster: &str) -> Result<Client, Error> {
         Client::try_default().await
     }
 }
+
+pub fn label_selector_string(selector: &LabelSelector) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(match_labels) = &selector.match_labels {
+        for (key, value) in match_labels {
+            parts.push(format!("{}={}", key, value));
+        }
+    }
+    if let Some(expressions) = &selector.match_expressions {
+        for expr in expressions {
+            let values = expr.values.clone().unwrap_or_default().join(",");
+            match expr.operator.as_str() {
+                "In" => parts.push(format!("{} in ({})", expr.key, values)),
+                "NotIn" => parts.push(format!("{} notin ({})", expr.key, values)),
+                "Exists" => parts.push(expr.key.to_string()),
+                "DoesNotExist" => parts.push(format!("!{}", expr.key)),
+                _ => {}
+            }
+        }
+    }
+    parts.join(",")
+}
+
+pub fn selector_matches(selector: &LabelSelector, labels: &BTreeMap<String, String>) -> bool {
+    if let Some(match_labels) = &selector.match_labels {
+        for (key, value) in match_labels {
+            if labels.get(key) != Some(value) {
+                return false;
+            }
+        }
+    }
+    if let Some(expressions) = &selector.match_expressions {
+        for expr in expressions {
+            let values = expr.values.clone().unwrap_or_default();
+            let value = labels.get(&expr.key);
+            let matched = match expr.operator.as_str() {
+                "In" => value.map(|v| values.contains(v)).unwrap_or(false),
+                "NotIn" => value.map(|v| !values.contains(v)).unwrap_or(true),
+                "Exists" => value.is_some(),
+                "DoesNotExist" => value.is_none(),
+                _ => false,
+            };
+            if !matched {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Resolves a kind or plural name through discovery and builds a dynamic api for it.
+/// Cluster scoped kinds, an empty namespace and `*All*` give an api across all namespaces.
+pub fn api_for_kind(
+    client: Client,
+    discovery: &Discovery,
+    kind: &str,
+    ns: &str,
+) -> Result<(Api<DynamicObject>, ApiResource, ApiCapabilities), String> {
+    let (ar, caps) = resolve_api_resource(discovery, kind.trim())
+        .ok_or_else(|| format!("Resource {} not found in cluster", kind))?;
+    let api = if caps.scope == Scope::Cluster || ns.is_empty() || ns == "*All*" {
+        Api::all_with(client, &ar)
+    } else {
+        Api::namespaced_with(client, ns, &ar)
+    };
+    Ok((api, ar, caps))
+}
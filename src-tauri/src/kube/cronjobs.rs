@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use chrono::Utc;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{Api, ListParams, ObjectMeta, Patch, PatchParams, PostParams, ResourceExt};
+use serde_json::json;
+use tauri::Window;
+use crate::kube::audit::Mutation;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::schedule::Schedule;
+use crate::utils::send_error;
+
+const INSTANTIATE_ANNOTATION: &str = "cronjob.kubernetes.io/instantiate";
+const MAX_JOB_NAME_PREFIX: usize = 49;
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct CronJobSchedule {
+    pub(crate) name: String,
+    pub(crate) ns: String,
+    pub(crate) schedule: String,
+    pub(crate) timezone: String,
+    pub(crate) suspended: bool,
+    pub(crate) last_schedule_time: Option<String>,
+    pub(crate) last_successful_time: Option<String>,
+    pub(crate) active: Vec<String>,
+    pub(crate) next_schedule_time: Option<String>,
+    pub(crate) error: Option<String>,
+}
+
+impl KubeClientManager {
+    pub fn trigger_cron_job(&self, window: &Window, ns: &str, name: &str, cmd: &str) {
+        let result = self._trigger_cron_job(window, ns, name, cmd);
+        if let Err(err) = result {
+            error!("Failed to trigger cron job {}: {}", name, err);
+            send_error(window, &format!("Failed to trigger cron job. Reason: {}", err));
+        }
+    }
+
+    /// Creates a Job from the CronJob template the same way `kubectl create job --from=cronjob/<name>` does.
+    #[tokio::main]
+    async fn _trigger_cron_job(&self, window: &Window, ns: &str, name: &str, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let cron_jobs: Api<CronJob> = self.get_api(client.clone(), ns);
+                let cron_job = cron_jobs.get(name).await?;
+                let job = _job_from_template(&cron_job)?;
+                let jobs: Api<Job> = self.get_api(client, ns);
+                let result = jobs.create(&PostParams::default(), &job).await;
+                // The server picks the name, so a failed create is recorded under the prefix
+                let job_name = match &result {
+                    Ok(created) => created.name_any(),
+                    Err(_) => job.metadata.generate_name.clone().unwrap_or_default(),
+                };
+                self.audit(Mutation::typed::<Job>("create", ns, &job_name), &result);
+                let created = result?;
+                info!("Triggered job {} from cron job {}", created.name_any(), name);
+                dispatch_to_frontend(window, cmd, created.name_any());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to trigger cron job. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_cron_job_suspended(&self, window: &Window, ns: &str, name: &str, suspend: bool, cmd: &str) {
+        let result = self._set_cron_job_suspended(window, ns, name, suspend, cmd);
+        if let Err(err) = result {
+            let action = if suspend { "suspend" } else { "resume" };
+            error!("Failed to {} cron job {}: {}", action, name, err);
+            send_error(window, &format!("Failed to {} cron job. Reason: {}", action, err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _set_cron_job_suspended(
+        &self,
+        window: &Window,
+        ns: &str,
+        name: &str,
+        suspend: bool,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let api: Api<CronJob> = self.get_api(client, ns);
+                let patch = Patch::Merge(json!({ "spec": { "suspend": suspend } }));
+                let result = api.patch(name, &PatchParams::default(), &patch).await;
+                let verb = if suspend { "suspend" } else { "resume" };
+                self.audit(Mutation::typed::<CronJob>(verb, ns, name), &result);
+                result?;
+                info!("Set suspend={} on cron job {}", suspend, name);
+                dispatch_to_frontend(window, cmd, "success".to_string());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to update cron job. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_cron_job_schedules(&self, window: &Window, ns: &str, cmd: &str) {
+        let result = self._get_cron_job_schedules(window, ns, cmd);
+        if let Err(err) = result {
+            error!("Failed to get cron job schedules: {}", err);
+            send_error(window, &format!("Failed to get cron job schedules. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _get_cron_job_schedules(&self, window: &Window, ns: &str, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let api: Api<CronJob> = self.get_api(client, ns);
+                let schedules: Vec<CronJobSchedule> = api
+                    .list(&ListParams::default())
+                    .await?
+                    .iter()
+                    .map(cron_job_schedule)
+                    .collect();
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&schedules).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to get cron job schedules. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(crate) fn cron_job_schedule(cron_job: &CronJob) -> CronJobSchedule {
+    let spec = cron_job.spec.clone().unwrap_or_default();
+    let status = cron_job.status.clone().unwrap_or_default();
+    let mut summary = CronJobSchedule {
+        name: cron_job.name_any(),
+        ns: cron_job.namespace().unwrap_or_default(),
+        schedule: spec.schedule.clone(),
+        suspended: spec.suspend.unwrap_or(false),
+        last_schedule_time: status.last_schedule_time.map(|t| t.0.to_rfc3339()),
+        last_successful_time: status.last_successful_time.map(|t| t.0.to_rfc3339()),
+        active: status.active.unwrap_or_default().into_iter().filter_map(|r| r.name).collect(),
+        ..CronJobSchedule::default()
+    };
+    let schedule = Schedule::parse(&spec.schedule).and_then(|s| s.with_default_timezone(spec.time_zone.as_deref()));
+    match schedule {
+        Ok(schedule) => {
+            summary.timezone = schedule.timezone().name().to_string();
+            if !summary.suspended {
+                summary.next_schedule_time = schedule.next_after(Utc::now()).map(|t| t.to_rfc3339());
+            }
+        },
+        Err(err) => summary.error = Some(err),
+    }
+    summary
+}
+
+fn _job_from_template(cron_job: &CronJob) -> Result<Job, String> {
+    let name = cron_job.name_any();
+    let template = cron_job
+        .spec
+        .as_ref()
+        .map(|s| s.job_template.clone())
+        .ok_or_else(|| format!("Cron job {} has no spec", name))?;
+    let template_meta = template.metadata.unwrap_or_default();
+    let mut annotations: BTreeMap<String, String> = template_meta.annotations.unwrap_or_default();
+    annotations.insert(INSTANTIATE_ANNOTATION.to_string(), "manual".to_string());
+    let owner = OwnerReference {
+        api_version: "batch/v1".to_string(),
+        kind: "CronJob".to_string(),
+        name: name.clone(),
+        uid: cron_job.uid().ok_or("Cron job has no uid")?,
+        controller: Some(true),
+        block_owner_deletion: Some(true),
+    };
+    // The job name becomes a label value on its pods, so it has to fit in 63 characters with the
+    // random suffix the server appends to generateName
+    let prefix: String = name.chars().take(MAX_JOB_NAME_PREFIX).collect();
+    Ok(Job {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-manual-", prefix)),
+            namespace: cron_job.namespace(),
+            labels: template_meta.labels,
+            annotations: Some(annotations),
+            owner_references: Some(vec![owner]),
+            ..ObjectMeta::default()
+        },
+        spec: template.spec,
+        status: None,
+    })
+}
@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use kube::api::{Api, DynamicObject, ListParams, ResourceExt};
+use kube::discovery::{verbs, ApiResource, Scope};
+use kube::{Client, Discovery};
+use tauri::Window;
+use crate::kube::common::{api_for_kind, dispatch_to_frontend};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::utils::send_error;
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct DependentRef {
+    pub(crate) kind: String,
+    pub(crate) ns: String,
+    pub(crate) name: String,
+    pub(crate) owner: String,
+    /// Also owned by objects that are not being deleted, so the garbage collector keeps it.
+    pub(crate) shared: bool,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct DeletePreview {
+    pub(crate) kind: String,
+    pub(crate) ns: String,
+    pub(crate) name: String,
+    pub(crate) total: usize,
+    pub(crate) counts: BTreeMap<String, usize>,
+    pub(crate) dependents: Vec<DependentRef>,
+    pub(crate) warnings: Vec<String>,
+}
+
+impl DeletePreview {
+    /// Shared dependents are listed for review but not counted, as they survive the delete.
+    fn push(&mut self, dependent: DependentRef) {
+        if !dependent.shared {
+            *self.counts.entry(dependent.kind.clone()).or_insert(0) += 1;
+            self.total += 1;
+        }
+        self.dependents.push(dependent);
+    }
+}
+
+impl KubeClientManager {
+    pub fn preview_delete(&self, window: &Window, ns: &str, kind: &str, name: &str, orphan: bool, cmd: &str) {
+        let result = self._preview_delete(window, ns, kind, name, orphan, cmd);
+        if let Err(err) = result {
+            error!("Failed to preview delete of {}: {}", name, err);
+            send_error(window, &format!("Failed to preview delete. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _preview_delete(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        orphan: bool,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let discovery = Discovery::new(client.clone()).run().await?;
+                let (api, ar, caps) = api_for_kind(client.clone(), &discovery, kind, ns)?;
+                let target = api.get(name).await?;
+                let mut preview = DeletePreview {
+                    kind: ar.kind.clone(),
+                    ns: target.namespace().unwrap_or_default(),
+                    name: name.to_string(),
+                    ..DeletePreview::default()
+                };
+                if ar.kind == "Namespace" {
+                    namespace_contents(client, &discovery, name, &mut preview).await;
+                } else if ar.kind == "CustomResourceDefinition" {
+                    custom_resources(client, &discovery, &target, &mut preview).await?;
+                } else if !orphan {
+                    let scope_ns = if caps.scope == Scope::Cluster { None } else { target.namespace() };
+                    owned_objects(client, &discovery, &target, scope_ns, &mut preview).await;
+                }
+                info!("Deleting {} {} would remove {} dependents", preview.kind, name, preview.total);
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&preview).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to preview delete. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Everything inside a namespace goes with it, whether or not it has owner references.
+async fn namespace_contents(client: Client, discovery: &Discovery, ns: &str, preview: &mut DeletePreview) {
+    for (ar, objects) in list_all(client, discovery, Some(ns), preview).await {
+        for obj in objects {
+            preview.push(DependentRef {
+                kind: ar.kind.clone(),
+                ns: ns.to_string(),
+                name: obj.name_any(),
+                owner: format!("Namespace/{}", ns),
+                shared: false,
+            });
+        }
+    }
+}
+
+/// Deleting a CRD removes every custom resource of that type across the cluster.
+async fn custom_resources(
+    client: Client,
+    discovery: &Discovery,
+    crd: &DynamicObject,
+    preview: &mut DeletePreview,
+) -> Result<(), Box<dyn Error>> {
+    let group = crd.data["spec"]["group"].as_str().unwrap_or_default();
+    let kind = crd.data["spec"]["names"]["kind"].as_str().unwrap_or_default();
+    let resource = discovery
+        .groups()
+        .filter(|g| g.name() == group)
+        .flat_map(|g| g.recommended_resources())
+        .find(|(ar, _caps)| ar.kind == kind);
+    let (ar, _caps) = match resource {
+        Some(resource) => resource,
+        None => {
+            preview.warnings.push(format!("{} is not served by the cluster", kind));
+            return Ok(());
+        }
+    };
+    let api: Api<DynamicObject> = Api::all_with(client, &ar);
+    for obj in api.list(&ListParams::default()).await? {
+        preview.push(DependentRef {
+            kind: ar.kind.clone(),
+            ns: obj.namespace().unwrap_or_default(),
+            name: obj.name_any(),
+            owner: format!("CustomResourceDefinition/{}", crd.name_any()),
+            shared: false,
+        });
+    }
+    Ok(())
+}
+
+/// Walks ownerReferences breadth first from the target, the same graph the garbage collector
+/// uses for cascading deletion. Namespaced owners only own objects in their namespace. The
+/// collector only removes an object once all of its owners are gone, so a child with an owner
+/// outside the deleted set is marked shared and not followed further.
+async fn owned_objects(
+    client: Client,
+    discovery: &Discovery,
+    target: &DynamicObject,
+    ns: Option<String>,
+    preview: &mut DeletePreview,
+) {
+    let mut children: HashMap<String, Vec<(String, DynamicObject)>> = HashMap::new();
+    for (ar, objects) in list_all(client, discovery, ns.as_deref(), preview).await {
+        for obj in objects {
+            for owner in obj.owner_references() {
+                children
+                    .entry(owner.uid.clone())
+                    .or_insert_with(Vec::new)
+                    .push((ar.kind.clone(), obj.clone()));
+            }
+        }
+    }
+
+    let target_uid = target.uid().unwrap_or_default();
+    let mut deleted: HashSet<String> = HashSet::new();
+    deleted.insert(target_uid.clone());
+    // Children with an owner not known to be deleted yet, with the owner they were reached from.
+    // They are checked again after each step, as the other owners may turn up later in the walk.
+    let mut pending: Vec<(String, DynamicObject, String)> = Vec::new();
+    let mut queue: VecDeque<(String, String)> = VecDeque::new();
+    queue.push_back((target_uid, format!("{}/{}", preview.kind, target.name_any())));
+    while let Some((uid, owner)) = queue.pop_front() {
+        if let Some(owned) = children.get(&uid) {
+            for (kind, obj) in owned {
+                let child_uid = obj.uid().unwrap_or_default();
+                if deleted.contains(&child_uid) || pending.iter().any(|(_, p, _)| p.uid() == obj.uid()) {
+                    continue;
+                }
+                pending.push((kind.clone(), obj.clone(), owner.clone()));
+            }
+        }
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending
+            .drain(..)
+            .partition(|(_, obj, _)| obj.owner_references().iter().all(|o| deleted.contains(&o.uid)));
+        pending = waiting;
+        for (kind, obj, owner) in ready {
+            let child_uid = obj.uid().unwrap_or_default();
+            let name = obj.name_any();
+            deleted.insert(child_uid.clone());
+            queue.push_back((child_uid, format!("{}/{}", kind, name)));
+            preview.push(DependentRef {
+                kind,
+                ns: obj.namespace().unwrap_or_default(),
+                name,
+                owner,
+                shared: false,
+            });
+        }
+    }
+    for (kind, obj, owner) in pending {
+        preview.push(DependentRef {
+            kind,
+            ns: obj.namespace().unwrap_or_default(),
+            name: obj.name_any(),
+            owner,
+            shared: true,
+        });
+    }
+}
+
+/// Lists every listable resource type in the namespace, or across the cluster when no namespace
+/// is given. Types that cannot be listed are reported as warnings rather than failing the preview.
+async fn list_all(
+    client: Client,
+    discovery: &Discovery,
+    ns: Option<&str>,
+    preview: &mut DeletePreview,
+) -> Vec<(ApiResource, Vec<DynamicObject>)> {
+    let mut results = Vec::new();
+    for group in discovery.groups() {
+        for (ar, caps) in group.recommended_resources() {
+            if !caps.supports_operation(verbs::LIST) || (ns.is_some() && caps.scope == Scope::Cluster) {
+                continue;
+            }
+            // Events expire on their own and would drown out the objects worth reviewing
+            if ar.kind == "Event" {
+                continue;
+            }
+            let api: Api<DynamicObject> = match ns {
+                Some(ns) => Api::namespaced_with(client.clone(), ns, &ar),
+                None => Api::all_with(client.clone(), &ar),
+            };
+            match api.list(&ListParams::default()).await {
+                Ok(list) => results.push((ar, list.items)),
+                Err(err) => preview.warnings.push(format!("Could not list {}: {}", ar.plural, err)),
+            }
+        }
+    }
+    results
+}
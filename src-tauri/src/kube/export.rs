@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use kube::api::{Api, DynamicObject, ListParams, ResourceExt};
+use kube::core::TypeMeta;
+use kube::discovery::{verbs, ApiResource, Scope};
+use kube::{Client, Discovery};
+use serde_json::Value;
+use tauri::Window;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::utils::send_error;
+
+/// Metadata the API server fills in. None of it can be sent back on a create.
+const SERVER_METADATA: [&str; 8] = [
+    "uid",
+    "resourceVersion",
+    "creationTimestamp",
+    "generation",
+    "managedFields",
+    "selfLink",
+    "deletionTimestamp",
+    "deletionGracePeriodSeconds",
+];
+const LAST_APPLIED_ANNOTATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupLayout {
+    /// One file per object under `<path>/<namespace>/<resource>/<name>.yaml`.
+    Directory,
+    /// Every object in one multi-document YAML file.
+    SingleFile,
+}
+
+#[derive(Clone, Debug)]
+pub struct BackupRequest {
+    pub(crate) ns: String,
+    /// Kinds or plural resource names to back up. Empty means everything in the namespace.
+    pub(crate) kinds: Vec<String>,
+    pub(crate) path: PathBuf,
+    pub(crate) layout: BackupLayout,
+    /// Objects with a controller owner are recreated by their owner, so they are left out by default.
+    pub(crate) include_owned: bool,
+}
+
+impl BackupRequest {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let ns = args.get("ns").cloned().filter(|ns| !ns.is_empty()).ok_or("A namespace is required")?;
+        let path = args.get("path").map(|p| p.trim()).filter(|p| !p.is_empty()).ok_or("A backup path is required")?;
+        let layout = match args.get("layout").map(|l| l.as_str()) {
+            None | Some("directory") => BackupLayout::Directory,
+            Some("file") | Some("single_file") => BackupLayout::SingleFile,
+            Some(other) => return Err(format!("Unknown backup layout {}", other)),
+        };
+        Ok(BackupRequest {
+            ns,
+            kinds: parse_kinds(args.get("kinds"))?,
+            path: expand_home(path),
+            layout,
+            include_owned: args.get("include_owned").map(|v| v == "true").unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct BackupReport {
+    pub(crate) ns: String,
+    pub(crate) path: String,
+    pub(crate) total: usize,
+    pub(crate) counts: BTreeMap<String, usize>,
+    pub(crate) skipped_owned: usize,
+    pub(crate) warnings: Vec<String>,
+}
+
+impl KubeClientManager {
+    pub fn backup_namespace(&self, window: &Window, request: BackupRequest, cmd: &str) {
+        let result = self._backup_namespace(window, &request, cmd);
+        if let Err(err) = result {
+            error!("Failed to back up namespace {}: {}", request.ns, err);
+            send_error(window, &format!("Failed to back up namespace. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _backup_namespace(&self, window: &Window, request: &BackupRequest, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let discovery = Discovery::new(client.clone()).run().await?;
+                let mut report = BackupReport {
+                    ns: request.ns.clone(),
+                    path: request.path.display().to_string(),
+                    ..BackupReport::default()
+                };
+                let objects = namespaced_objects(client, &discovery, &request.ns, &request.kinds, &mut report.warnings).await;
+                let mut documents: Vec<(ApiResource, Value)> = Vec::new();
+                for (ar, obj) in objects {
+                    if !request.include_owned && obj.owner_references().iter().any(|o| o.controller == Some(true)) {
+                        report.skipped_owned += 1;
+                        continue;
+                    }
+                    *report.counts.entry(ar.kind.clone()).or_insert(0) += 1;
+                    report.total += 1;
+                    documents.push((ar.clone(), export_value(&ar, &obj)));
+                }
+                _write_backup(request, &documents)?;
+                info!("Backed up {} objects from {} to {}", report.total, request.ns, report.path);
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&report).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to back up namespace. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Serializes an object the way it would be written by hand: with its type, and without the
+/// fields the cluster assigns or tracks for it.
+pub(crate) fn export_value(ar: &ApiResource, obj: &DynamicObject) -> Value {
+    let mut obj = obj.clone();
+    obj.types = Some(TypeMeta {
+        api_version: ar.api_version.clone(),
+        kind: ar.kind.clone(),
+    });
+    let mut value = serde_json::to_value(&obj).unwrap_or(Value::Null);
+    clean_for_export(&mut value);
+    value
+}
+
+/// Strips status, server populated metadata, the last applied annotation and the cluster IPs a
+/// Service was given by default. Headless services keep their `None` cluster IP.
+pub(crate) fn clean_for_export(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("status");
+    }
+    if let Some(metadata) = value["metadata"].as_object_mut() {
+        for field in SERVER_METADATA.iter() {
+            metadata.remove(*field);
+        }
+        let empty = match metadata.get_mut("annotations").and_then(|a| a.as_object_mut()) {
+            Some(annotations) => {
+                annotations.remove(LAST_APPLIED_ANNOTATION);
+                annotations.is_empty()
+            },
+            None => false,
+        };
+        if empty {
+            metadata.remove("annotations");
+        }
+    }
+    if value["kind"].as_str() == Some("Service") {
+        if let Some(spec) = value["spec"].as_object_mut() {
+            if spec.get("clusterIP").and_then(|ip| ip.as_str()) != Some("None") {
+                spec.remove("clusterIP");
+                spec.remove("clusterIPs");
+            }
+        }
+    }
+}
+
+/// Lists the objects of every namespaced, listable type in a namespace, limited to the given
+/// kinds when there are any. Types that cannot be listed are reported as warnings.
+pub(crate) async fn namespaced_objects(
+    client: Client,
+    discovery: &Discovery,
+    ns: &str,
+    kinds: &[String],
+    warnings: &mut Vec<String>,
+) -> Vec<(ApiResource, DynamicObject)> {
+    let mut results = Vec::new();
+    for group in discovery.groups() {
+        for (ar, caps) in group.recommended_resources() {
+            if !caps.supports_operation(verbs::LIST) || caps.scope == Scope::Cluster {
+                continue;
+            }
+            let wanted = if kinds.is_empty() {
+                // Events and the like are records of what happened, not desired state
+                !["Event", "Endpoints", "EndpointSlice", "Lease"].contains(&ar.kind.as_str())
+            } else {
+                kinds.iter().any(|k| k.eq_ignore_ascii_case(&ar.kind) || k.eq_ignore_ascii_case(&ar.plural))
+            };
+            if !wanted {
+                continue;
+            }
+            let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, &ar);
+            match api.list(&ListParams::default()).await {
+                Ok(list) => {
+                    for obj in list.items {
+                        results.push((ar.clone(), obj));
+                    }
+                },
+                Err(err) => warnings.push(format!("Could not list {}: {}", ar.plural, err)),
+            }
+        }
+    }
+    results
+}
+
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Accepts kinds as a JSON array or a comma separated list.
+pub(crate) fn parse_kinds(kinds: Option<&String>) -> Result<Vec<String>, String> {
+    match kinds.map(|k| k.trim()) {
+        None | Some("") => Ok(Vec::new()),
+        Some(kinds) if kinds.starts_with('[') => {
+            serde_json::from_str(kinds).map_err(|e| format!("Invalid kinds: {}", e))
+        },
+        Some(kinds) => Ok(kinds
+            .split(',')
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty())
+            .collect()),
+    }
+}
+
+fn _write_backup(request: &BackupRequest, documents: &[(ApiResource, Value)]) -> Result<(), Box<dyn Error>> {
+    match request.layout {
+        BackupLayout::SingleFile => {
+            if let Some(dir) = request.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut out = String::new();
+            for (_ar, value) in documents {
+                out.push_str(&_to_document(value)?);
+            }
+            fs::write(&request.path, out)?;
+        },
+        BackupLayout::Directory => {
+            for (ar, value) in documents {
+                let name = value["metadata"]["name"].as_str().unwrap_or_default();
+                let mut dir = request.path.join(&request.ns);
+                // Kinds from different groups can share a name, so the group is kept apart
+                dir.push(if ar.group.is_empty() {
+                    ar.plural.clone()
+                } else {
+                    format!("{}.{}", ar.plural, ar.group)
+                });
+                fs::create_dir_all(&dir)?;
+                fs::write(dir.join(format!("{}.yaml", name)), _to_document(value)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn _to_document(value: &Value) -> Result<String, serde_yaml::Error> {
+    let mut yaml = serde_yaml::to_string(value)?;
+    // serde_yaml 0.8 already starts each document with a separator
+    if !yaml.starts_with("---") {
+        yaml.insert_str(0, "---\n");
+    }
+    if !yaml.ends_with('\n') {
+        yaml.push('\n');
+    }
+    Ok(yaml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cleaned(mut value: Value) -> Value {
+        clean_for_export(&mut value);
+        value
+    }
+
+    #[test]
+    fn strips_status_and_server_metadata() {
+        let deployment = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "name": "web",
+                "namespace": "shop",
+                "labels": { "app": "web" },
+                "uid": "6f1c2c4e-0000-4000-8000-000000000000",
+                "resourceVersion": "4821",
+                "creationTimestamp": "2024-03-01T10:00:00Z",
+                "generation": 3,
+                "managedFields": [{ "manager": "kubectl" }],
+                "selfLink": "/apis/apps/v1/namespaces/shop/deployments/web",
+            },
+            "spec": { "replicas": 2 },
+            "status": { "readyReplicas": 2 },
+        });
+        let expected = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "shop", "labels": { "app": "web" } },
+            "spec": { "replicas": 2 },
+        });
+        assert_eq!(cleaned(deployment), expected);
+    }
+
+    #[test]
+    fn removes_annotations_once_empty() {
+        let annotated = |annotations: Value| {
+            json!({ "kind": "ConfigMap", "metadata": { "name": "settings", "annotations": annotations } })
+        };
+        let cases = [
+            (
+                annotated(json!({ LAST_APPLIED_ANNOTATION: "{}" })),
+                json!({ "kind": "ConfigMap", "metadata": { "name": "settings" } }),
+            ),
+            (annotated(json!({})), json!({ "kind": "ConfigMap", "metadata": { "name": "settings" } })),
+            (
+                annotated(json!({ LAST_APPLIED_ANNOTATION: "{}", "team": "checkout" })),
+                annotated(json!({ "team": "checkout" })),
+            ),
+        ];
+        for (value, expected) in cases.iter() {
+            assert_eq!(cleaned(value.clone()), *expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn keeps_the_cluster_ip_of_headless_services_only() {
+        let service = |spec: Value| json!({ "kind": "Service", "metadata": { "name": "db" }, "spec": spec });
+        let cases = [
+            (
+                service(json!({ "clusterIP": "None", "clusterIPs": ["None"], "ports": [{ "port": 5432 }] })),
+                service(json!({ "clusterIP": "None", "clusterIPs": ["None"], "ports": [{ "port": 5432 }] })),
+            ),
+            (
+                service(json!({ "clusterIP": "10.96.12.4", "clusterIPs": ["10.96.12.4"], "ports": [{ "port": 5432 }] })),
+                service(json!({ "ports": [{ "port": 5432 }] })),
+            ),
+            (
+                service(json!({ "type": "ExternalName", "externalName": "db.example.com" })),
+                service(json!({ "type": "ExternalName", "externalName": "db.example.com" })),
+            ),
+        ];
+        for (value, expected) in cases.iter() {
+            assert_eq!(cleaned(value.clone()), *expected, "{}", value);
+        }
+        // Only Services are touched
+        let pod = json!({ "kind": "Pod", "metadata": { "name": "db" }, "spec": { "clusterIP": "10.0.0.1" } });
+        assert_eq!(cleaned(pod.clone()), pod);
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::PodTemplateSpec;
+use kube::api::{Api, Patch, PatchParams};
+use kube::Client;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+use tauri::Window;
+use crate::kube::audit::Mutation;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::rollout::WorkloadKind;
+use crate::utils::send_error;
+
+const MAX_IMAGE_NAME_LENGTH: usize = 255;
+/// The reference grammar from the distribution project: an optional registry host, a path of
+/// lowercase components, an optional tag and an optional digest.
+const IMAGE_REFERENCE_GRAMMAR: &str = concat!(
+    r"^(?:(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9])(?:\.(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9]))*(?::[0-9]+)?/)?",
+    r"[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*(?:/[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*)*",
+    r"(?::[A-Za-z0-9_][A-Za-z0-9_.-]{0,127})?",
+    r"(?:@[A-Za-z][A-Za-z0-9]*(?:[-_+.][A-Za-z][A-Za-z0-9]*)*:[0-9a-fA-F]{32,})?$"
+);
+static IMAGE_REFERENCE_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(IMAGE_REFERENCE_GRAMMAR).unwrap());
+
+#[derive(Clone, Debug, Default)]
+pub struct ImageUpdate {
+    pub(crate) images: BTreeMap<String, String>,
+}
+
+impl ImageUpdate {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let images = args.get("images").ok_or("No images were provided")?;
+        let images: BTreeMap<String, String> =
+            serde_json::from_str(images).map_err(|e| format!("Invalid images: {}", e))?;
+        if images.is_empty() {
+            return Err("No images were provided".to_string());
+        }
+        for image in images.values() {
+            validate_image_reference(image)?;
+        }
+        Ok(ImageUpdate { images })
+    }
+}
+
+impl KubeClientManager {
+    pub fn set_image(&self, window: &Window, ns: &str, kind: &str, name: &str, update: ImageUpdate, cmd: &str) {
+        let result = self._set_image(window, ns, kind, name, &update, cmd);
+        if let Err(err) = result {
+            error!("Failed to set image on {}: {}", name, err);
+            send_error(window, &format!("Failed to set image. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _set_image(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        update: &ImageUpdate,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let workload = WorkloadKind::from_kind(kind)
+                    .ok_or_else(|| format!("Setting images is not supported for {}", kind))?;
+                let template = self.pod_template(client.clone(), ns, workload, name).await?;
+                let patch = image_patch(&template, &update.images)?;
+                let params = PatchParams::default();
+                let patch = Patch::Strategic(patch);
+                match workload {
+                    WorkloadKind::Deployment => {
+                        let api: Api<Deployment> = self.get_api(client.clone(), ns);
+                        let result = api.patch(name, &params, &patch).await;
+                        self.audit(Mutation::typed::<Deployment>("set_image", ns, name), &result);
+                        result?;
+                    },
+                    WorkloadKind::StatefulSet => {
+                        let api: Api<StatefulSet> = self.get_api(client.clone(), ns);
+                        let result = api.patch(name, &params, &patch).await;
+                        self.audit(Mutation::typed::<StatefulSet>("set_image", ns, name), &result);
+                        result?;
+                    },
+                    WorkloadKind::DaemonSet => {
+                        let api: Api<DaemonSet> = self.get_api(client.clone(), ns);
+                        let result = api.patch(name, &params, &patch).await;
+                        self.audit(Mutation::typed::<DaemonSet>("set_image", ns, name), &result);
+                        result?;
+                    }
+                }
+                info!("Set images {:?} on {:?} {}", update.images, workload, name);
+                dispatch_to_frontend(window, cmd, "success".to_string());
+                let tracked = self.track_rollout(window, client, ns, workload, name, None).await;
+                if let Err(err) = tracked {
+                    warn!("Failed to track rollout of {}: {}", name, err);
+                }
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to set image. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    async fn pod_template(
+        &self,
+        client: Client,
+        ns: &str,
+        workload: WorkloadKind,
+        name: &str,
+    ) -> Result<PodTemplateSpec, Box<dyn Error>> {
+        let template = match workload {
+            WorkloadKind::Deployment => {
+                let api: Api<Deployment> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| s.template)
+            },
+            WorkloadKind::StatefulSet => {
+                let api: Api<StatefulSet> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| s.template)
+            },
+            WorkloadKind::DaemonSet => {
+                let api: Api<DaemonSet> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| s.template)
+            }
+        };
+        Ok(template.unwrap_or_default())
+    }
+}
+
+/// Builds a strategic merge patch that only touches the named containers. Containers are merged
+/// by name, so the other containers and fields in the template are left as they are.
+fn image_patch(template: &PodTemplateSpec, images: &BTreeMap<String, String>) -> Result<Value, String> {
+    let spec = template.spec.clone().unwrap_or_default();
+    let init_names: Vec<String> = spec.init_containers.unwrap_or_default().into_iter().map(|c| c.name).collect();
+    let names: Vec<String> = spec.containers.into_iter().map(|c| c.name).collect();
+    let mut containers = Vec::new();
+    let mut init_containers = Vec::new();
+    for (container, image) in images {
+        if names.contains(container) {
+            containers.push(json!({ "name": container, "image": image }));
+        } else if init_names.contains(container) {
+            init_containers.push(json!({ "name": container, "image": image }));
+        } else {
+            return Err(format!("Container {} not found", container));
+        }
+    }
+    let mut pod_spec = json!({});
+    if !containers.is_empty() {
+        pod_spec["containers"] = json!(containers);
+    }
+    if !init_containers.is_empty() {
+        pod_spec["initContainers"] = json!(init_containers);
+    }
+    Ok(json!({ "spec": { "template": { "spec": pod_spec } } }))
+}
+
+pub(crate) fn validate_image_reference(image: &str) -> Result<(), String> {
+    if image.trim().is_empty() {
+        return Err("Image must not be empty".to_string());
+    }
+    let name = image.split('@').next().unwrap_or_default();
+    // A colon after the last slash starts the tag, one before it is a registry port
+    let name = match (name.rfind(':'), name.rfind('/')) {
+        (Some(colon), Some(slash)) if colon > slash => &name[..colon],
+        (Some(colon), None) => &name[..colon],
+        _ => name,
+    };
+    if name.len() > MAX_IMAGE_NAME_LENGTH {
+        return Err(format!("Image name must be at most {} characters", MAX_IMAGE_NAME_LENGTH));
+    }
+    if !IMAGE_REFERENCE_PATTERN.is_match(image) {
+        return Err(format!("'{}' is not a valid image reference", image));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn validates_image_references() {
+        let cases = [
+            ("nginx".to_string(), true),
+            ("nginx:1.25-alpine".to_string(), true),
+            ("library/nginx:stable".to_string(), true),
+            ("registry.example.com:5000/team/app:v1.2".to_string(), true),
+            ("localhost:5000/app".to_string(), true),
+            (format!("ghcr.io/org/app@{}", DIGEST), true),
+            (format!("ghcr.io/org/app:v1@{}", DIGEST), true),
+            ("a".repeat(255), true),
+            (format!("{}:v1", "a".repeat(255)), true),
+            ("a".repeat(256), false),
+            (format!("registry.example.com/{}", "a".repeat(235)), false),
+            ("ghcr.io/Org/App:v1".to_string(), false),
+            ("Nginx".to_string(), false),
+            ("ghcr.io/org/app@sha256:short".to_string(), false),
+            ("registry.example.com:port/app".to_string(), false),
+            ("app:".to_string(), false),
+            ("app:-tag".to_string(), false),
+            ("   ".to_string(), false),
+        ];
+        for (image, valid) in cases.iter() {
+            assert_eq!(validate_image_reference(image).is_ok(), *valid, "{}", image);
+        }
+        assert_eq!(
+            validate_image_reference(&"a".repeat(256)),
+            Err(format!("Image name must be at most {} characters", MAX_IMAGE_NAME_LENGTH))
+        );
+    }
+
+    fn template() -> PodTemplateSpec {
+        serde_json::from_value(json!({
+            "spec": {
+                "initContainers": [{ "name": "migrate" }],
+                "containers": [{ "name": "app" }, { "name": "proxy" }],
+            }
+        }))
+        .unwrap()
+    }
+
+    fn images(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(container, image)| (container.to_string(), image.to_string())).collect()
+    }
+
+    #[test]
+    fn splits_containers_and_init_containers() {
+        let cases = [
+            (
+                images(&[("app", "app:v2")]),
+                json!({ "spec": { "template": { "spec": { "containers": [{ "name": "app", "image": "app:v2" }] } } } }),
+            ),
+            (
+                images(&[("migrate", "app:v2")]),
+                json!({ "spec": { "template": { "spec": { "initContainers": [{ "name": "migrate", "image": "app:v2" }] } } } }),
+            ),
+            (
+                images(&[("app", "app:v2"), ("migrate", "app:v2"), ("proxy", "envoy:1.30")]),
+                json!({ "spec": { "template": { "spec": {
+                    "containers": [{ "name": "app", "image": "app:v2" }, { "name": "proxy", "image": "envoy:1.30" }],
+                    "initContainers": [{ "name": "migrate", "image": "app:v2" }],
+                } } } }),
+            ),
+        ];
+        for (images, expected) in cases.iter() {
+            assert_eq!(image_patch(&template(), images).as_ref(), Ok(expected), "{:?}", images);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_containers() {
+        let result = image_patch(&template(), &images(&[("app", "app:v2"), ("worker", "app:v2")]));
+        assert_eq!(result, Err("Container worker not found".to_string()));
+    }
+}
@@ -26,11 +26,14 @@ use kube::discovery::ApiResource;
 use tauri::Window;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 use crate::{CommandResult, KNamespace, utils};
-use crate::kube::common::dispatch_to_frontend;
+use crate::kube::audit::Mutation;
+use crate::kube::common::{api_for_kind, dispatch_to_frontend};
 use crate::kube::metrics::{PodMetrics};
-use crate::kube::models::{Metric, NodeMetrics, ResourceWithMetricsHolder};
+use crate::kube::models::{DeleteOptions, DeleteStatus, Metric, NodeMetrics, ResourceWithMetricsHolder};
 use crate::kube::{models, Payload};
-use tokio::time::{sleep, Duration};
+use crate::kube::rollout::WorkloadKind;
+use tokio::time::{sleep, timeout, Duration};
+use kube::runtime::wait::{await_condition, conditions::is_deleted};
 use crate::utils::send_error;
 use tokio::io;
 use std::task::Context;
@@ -82,6 +85,28 @@ impl KubeClientManager {
         km
     }
 
+    /// The context mutations are made in. An empty cluster means the kubeconfig current context.
+    pub(crate) fn context(&self) -> String {
+        if self.cluster.len() > 0 {
+            return self.cluster.clone();
+        }
+        Kubeconfig::read()
+            .ok()
+            .and_then(|kc| kc.current_context)
+            .unwrap_or_default()
+    }
+
+    /// A manager for another context using the same kubeconfig. Unlike `set_cluster` it does not
+    /// probe the metrics server, so it can be created from async code.
+    pub(crate) fn for_context(&self, context: &str) -> KubeClientManager {
+        KubeClientManager {
+            cluster: context.to_string(),
+            kubeconfigfile: self.kubeconfigfile.clone(),
+            proxy_url: self.proxy_url.clone(),
+            is_metrics_server_running: false
+        }
+    }
+
     pub fn set_cluster(&mut self, cl: &str) {
         self.cluster = cl.to_string();
         self._check_metrics_server();
@@ -91,7 +116,7 @@ impl KubeClientManager {
         self.kubeconfigfile = file.to_string();
     }
 
-    fn get_api<T>(&self, client: Client, ns: &str) -> Api<T> where T: Resource + k8s_openapi::Metadata<Ty = ObjectMeta>{
+    pub(crate) fn get_api<T>(&self, client: Client, ns: &str) -> Api<T> where T: Resource + k8s_openapi::Metadata<Ty = ObjectMeta>{
         if ns == "*All*" {
             return Api::all(client);
         }
@@ -129,7 +154,7 @@ impl KubeClientManager {
         }
     }
 
-    async fn init_client(&self) -> Option<Client> {
+    pub(crate) async fn init_client(&self) -> Option<Client> {
         if self.cluster.len() > 0 {
             let kco = KubeConfigOptions {
                 context: Some(self.cluster.parse().unwrap()),
@@ -288,6 +313,7 @@ impl KubeClientManager {
                         metrics: serde_json::to_string(&metrics).unwrap(),
                         usage: Some(serde_json::to_string(&pods).unwrap()),
                         metrics2: None,
+                        paused: None,
                         ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
                     };
                     let json = serde_json::to_string(&metrics).unwrap();
@@ -344,6 +370,7 @@ impl KubeClientManager {
                     metrics: metrics_val,
                     usage: None,
                     metrics2: None,
+                    paused: None,
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
@@ -383,6 +410,7 @@ impl KubeClientManager {
                     metrics: metrics_val,
                     usage: None,
                     metrics2: None,
+                    paused: None,
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
                 };
                 let result = serde_json::to_string(&json).unwrap();
@@ -439,6 +467,7 @@ impl KubeClientManager {
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
                     metrics: metrics_val,
                     metrics2,
+                    paused: None,
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
                 Ok(())
@@ -490,6 +519,7 @@ impl KubeClientManager {
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
                     metrics: metrics_val,
                     metrics2,
+                    paused: None,
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
                 Ok(())
@@ -541,6 +571,7 @@ impl KubeClientManager {
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
                     metrics: metrics_val,
                     metrics2,
+                    paused: None,
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
                 Ok(())
@@ -588,6 +619,7 @@ impl KubeClientManager {
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
                     metrics: metrics_val,
                     metrics2,
+                    paused: None,
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
                 Ok(())
@@ -618,6 +650,12 @@ impl KubeClientManager {
                 let lp = ListParams::default();
                 let pods = p_kube_request.list(&lp).await?;
 
+                let paused: Vec<String> = deployments
+                    .iter()
+                    .filter(|d| d.spec.as_ref().and_then(|s| s.paused).unwrap_or(false))
+                    .map(|d| d.name_any())
+                    .collect();
+
                 let mut metrics_val = "".to_string();
                 let mut metrics2 = None;
                 if self.is_metrics_available() {
@@ -639,6 +677,7 @@ impl KubeClientManager {
                     ts: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
                     metrics: metrics_val,
                     metrics2,
+                    paused: Some(paused),
                 };
                 dispatch_to_frontend(window, cmd, serde_json::to_string(&json).unwrap());
                 Ok(())
@@ -696,21 +735,31 @@ impl KubeClientManager {
     #[tokio::main]
     pub async fn edit_resource(
         &self,
+        window: &Window,
         ns: &str,
         resource_str: &str,
         name: &str,
-        kind: &str
+        kind: &str,
+        validate: bool
     ) -> bool  {
         let client = self.init_client().await;
 
         match client {
             Some(cl) => {
+                if validate && !self.check_manifests(window, cl.clone(), resource_str).await {
+                    return false;
+                }
                 let createRequest: Api<DynamicObject> = self._build_api(ns, kind, cl.clone());
 
                 let params = PatchParams::apply("yaki").force();
                 let patch: DynamicObject = serde_yaml::from_str(resource_str).unwrap();
+                let mut mutation = Mutation::for_object("edit", &patch, ns);
+                if let Ok(before) = createRequest.get(name).await {
+                    mutation = mutation.before(&before);
+                }
                 let patch = Patch::Apply(&patch);
                 let o_patched = createRequest.patch(name, &params, &patch).await;
+                self.audit(mutation, &o_patched);
                 match o_patched {
                     Ok(res) => {
                         true
@@ -783,9 +832,10 @@ impl KubeClientManager {
         resource_str: &str,
         kind: &str,
         ns: Option<&String>,
+        validate: bool,
         cmd: &str
     ) {
-        self._create_resource(window, resource_str, kind, ns, cmd);
+        self._create_resource(window, resource_str, kind, ns, validate, cmd);
     }
 
     #[tokio::main]
@@ -795,6 +845,7 @@ impl KubeClientManager {
         resource_str: &str,
         kind: &str,
         nso: Option<&String>,
+        validate: bool,
         cmd: &str
     ) -> bool  {
         let mut ns = "";
@@ -804,6 +855,9 @@ impl KubeClientManager {
         let client = self.init_client().await;
         match client {
             Some(cl) => {
+                if validate && !self.check_manifests(window, cl.clone(), resource_str).await {
+                    return false;
+                }
                 let docs = self.multidoc_deserialize(resource_str);
                 if docs.is_empty() {
                     send_error(window, "No resource found. Check if Yaml is valid");
@@ -813,8 +867,10 @@ impl KubeClientManager {
                     let params = PostParams::default();
                     let create_request: Api<DynamicObject> = self._build_api(ns, kind, cl.clone());
                     let o_patched = create_request.create(&params, &patch).await;
+                    self.audit(Mutation::for_object("create", &patch, ns), &o_patched);
                     match o_patched {
-                        Ok(_res) => {
+                        Ok(res) => {
+                            self.track_applied_object(window, cl.clone(), &res, ns).await;
                             true
                         },
                         Err(e) => {
@@ -823,6 +879,7 @@ impl KubeClientManager {
                         }
                     }
                 }else{
+                    let mut created: Vec<DynamicObject> = Vec::new();
                     for doc in docs {
                         let patch: Result<DynamicObject, serde_yaml::Error> = serde_yaml::from_value(doc);
                         if let Ok(patch) = patch {
@@ -830,9 +887,10 @@ impl KubeClientManager {
                             if let Some(tm) = &patch.types {
                                 let create_request: Api<DynamicObject> = self._build_api(ns, &tm.kind, cl.clone());
                                 let o_patched = create_request.create(&params, &patch).await;
+                                self.audit(Mutation::for_object("create", &patch, ns), &o_patched);
                                 match o_patched {
-                                    Ok(_res) => {
-
+                                    Ok(res) => {
+                                        created.push(res);
                                     },
                                     Err(e) => {
                                         send_error(window, &e.to_string());
@@ -845,6 +903,10 @@ impl KubeClientManager {
                             println!("Skipping: ");
                         }
                     }
+                    let tracking = created
+                        .iter()
+                        .map(|res| self.track_applied_object(window, cl.clone(), res, ns));
+                    futures::future::join_all(tracking).await;
                     true
                 }
             },
@@ -871,9 +933,10 @@ impl KubeClientManager {
         ns: &str,
         resource_name: &str,
         kind: &str,
+        options: DeleteOptions,
         cmd: &str
     ) {
-        self._delete_resource(window, ns, resource_name, kind, cmd);
+        self._delete_resource(window, ns, resource_name, kind, &options, cmd);
     }
 
     fn _build_api(
@@ -903,91 +966,84 @@ impl KubeClientManager {
         ns: &str,
         resource_name: &str,
         kind: &str,
+        options: &DeleteOptions,
         cmd: &str
     ) -> bool  {
         let client = self.init_client().await;
 
         match client {
             Some(cl) => {
-                let deleteapi: Api<DynamicObject> = self._build_api(ns, kind, cl.clone());
+                let discovery = Discovery::new(cl.clone()).run().await;
+                let (deleteapi, ar) = match discovery.map_err(|e| e.to_string())
+                    .and_then(|d| api_for_kind(cl.clone(), &d, kind, ns)) {
+                    Ok((api, ar, _caps)) => (api, ar),
+                    Err(e) => {
+                        send_error(window, &format!("Failed to delete {}. Reason: {}", resource_name, e));
+                        return false;
+                    }
+                };
 
-                let params = DeleteParams::default();
-                let res = deleteapi.delete(resource_name, &params).await;
-                match res {
-                    Ok(res) => {
+                let mut status = DeleteStatus {
+                    kind: kind.to_string(),
+                    ns: ns.to_string(),
+                    name: resource_name.to_string(),
+                    ..DeleteStatus::default()
+                };
+                let mut mutation = Mutation::new("delete", &ar.api_version, &ar.kind, ns, resource_name);
+                if let Ok(before) = deleteapi.get(resource_name).await {
+                    mutation = mutation.before(&before);
+                }
+                let res = deleteapi.delete(resource_name, &options.params()).await;
+                self.audit(mutation, &res.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+                let deleted = match res {
+                    Ok(either::Either::Left(obj)) => {
+                        _emit_delete_status(window, &mut status, "terminating", "Waiting for the resource to be removed");
+                        let uid = obj.uid().unwrap_or_default();
+                        let condition = await_condition(deleteapi.clone(), resource_name, is_deleted(&uid));
+                        match timeout(Duration::from_secs(options.timeout), condition).await {
+                            Ok(Ok(_)) => {
+                                _emit_delete_status(window, &mut status, "deleted", "Resource deleted");
+                                true
+                            },
+                            Ok(Err(e)) => {
+                                _emit_delete_status(window, &mut status, "failed", &e.to_string());
+                                false
+                            },
+                            Err(_) => {
+                                let finalizers = deleteapi
+                                    .get(resource_name)
+                                    .await
+                                    .map(|o| o.finalizers().to_vec())
+                                    .unwrap_or_default();
+                                let mut message = format!("Still terminating after {} seconds", options.timeout);
+                                if !finalizers.is_empty() {
+                                    message = format!("{}. Blocked by finalizers: {}", message, finalizers.join(", "));
+                                }
+                                _emit_delete_status(window, &mut status, "timeout", &message);
+                                send_error(window, &format!("Failed to delete {}. Reason: {}", resource_name, message));
+                                false
+                            }
+                        }
+                    },
+                    Ok(either::Either::Right(_)) => {
+                        _emit_delete_status(window, &mut status, "deleted", "Resource deleted");
                         true
                     },
                     Err(e) => {
-                        println!("{:?}", e);
+                        error!("Failed to delete {}: {:?}", resource_name, e);
+                        send_error(window, &format!("Failed to delete {}. Reason: {}", resource_name, e));
                         false
                     }
+                };
+                if deleted {
+                    dispatch_to_frontend(window, cmd, "success".to_string());
                 }
+                deleted
             },
             None => false
         }
     }
 
-    pub fn tail_logs_for_pod(
-        &self,
-        window: Window,
-        pod: &str,
-        ns: &str,
-        rx: &Receiver<String>,
-    ) {
-        self._tail_logs_for_pod(window,  pod, ns, rx);
-    }
-
-    #[tokio::main]
-    async fn _tail_logs_for_pod(
-        &self,
-        window: Window,
-        pod: &str,
-        ns: &str,
-        rx: &Receiver<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Fetching logs for {:?}", pod);
-        let client = self.init_client().await;
-        match client {
-            Some(client) => {
-                let pods: Api<Pod> = self.get_api(client, ns);
-                let mut logs = pods
-                    .log_stream(
-                        &pod,
-                        &LogParams {
-                            follow: true,
-                            tail_lines: Some(1),
-                            ..LogParams::default()
-                        },
-                    )
-                    .await?
-                    .boxed();
-
-                debug!("Spawning task");
-                while let Some(line) = logs.try_next().await? {
-                    let line_str = String::from_utf8_lossy(&line);
-                    debug!("{:?}", line_str);
-                    let stopword = rx.try_recv().unwrap_or("ERR".to_string());
-                    if stopword != "ERR" {
-                        debug!("Work is done: {:?}", stopword);
-                        break;
-                    }
-                    window
-                        .emit(
-                            "dashboard::logs",
-                            Payload {
-                                message: line_str.to_string(),
-                                metadata: String::from(pod),
-                            },
-                        )
-                        .unwrap();
-                }
-                debug!("Finished spawned task");
-                Ok(())
-            },
-                None => Ok(())
-        }
-    }
-
     pub fn open_shell(
         &self,
         window: &Window,
@@ -1132,8 +1188,10 @@ impl KubeClientManager {
         let client = self.init_client().await;
         match client {
             Some(client) => {
-                let deploy_request: Api<Deployment> = self.get_api(client, namespace);
-                let result = deploy_request.restart(deployment).await?;
+                let deploy_request: Api<Deployment> = self.get_api(client.clone(), namespace);
+                let result = deploy_request.restart(deployment).await;
+                self.audit(Mutation::typed::<Deployment>("restart", namespace, deployment), &result);
+                result?;
                 let json = "success";
                 window
                     .emit(
@@ -1144,6 +1202,10 @@ impl KubeClientManager {
                         },
                     )
                     .unwrap();
+                let tracked = self.track_rollout(window, client, namespace, WorkloadKind::Deployment, deployment, None).await;
+                if let Err(err) = tracked {
+                    warn!("Failed to track rollout of {}: {}", deployment, err);
+                }
                 Ok(())
             },
             None => {
@@ -1359,6 +1421,20 @@ impl KubeClientManager {
     }
 }
 
+fn _emit_delete_status(window: &Window, status: &mut DeleteStatus, state: &str, message: &str) {
+    status.status = state.to_string();
+    status.message = message.to_string();
+    window
+        .emit(
+            "app::delete_status",
+            Payload {
+                message: serde_json::to_string(status).unwrap(),
+                metadata: status.name.clone(),
+            },
+        )
+        .unwrap();
+}
+
 fn _get_current_cluster(filename: &String) -> String {
     let kc = Kubeconfig::read_from(Path::new(filename));
     match kc {
@@ -56,7 +56,7 @@ enum Verb {
     Apply,
 }
 
-fn resolve_api_resource(
+pub(crate) fn resolve_api_resource(
     discovery: &Discovery,
     name: &str,
 ) -> Option<(ApiResource, ApiCapabilities)> {
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use kube::api::{Patch, PatchParams, ResourceExt};
+use kube::Discovery;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+use tauri::Window;
+use crate::kube::audit::Mutation;
+use crate::kube::common::{api_for_kind, dispatch_to_frontend};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::rollout::WorkloadKind;
+use crate::utils::send_error;
+
+const MAX_PREFIX_LENGTH: usize = 253;
+const MAX_NAME_LENGTH: usize = 63;
+const MAX_ANNOTATIONS_SIZE: usize = 256 * 1024;
+static NAME_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9]([-A-Za-z0-9_.]*[A-Za-z0-9])?$").unwrap());
+static DNS_SUBDOMAIN_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?(\.[a-z0-9]([-a-z0-9]*[a-z0-9])?)*$").unwrap());
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum MetadataField {
+    Labels,
+    Annotations,
+}
+
+impl MetadataField {
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            MetadataField::Labels => "labels",
+            MetadataField::Annotations => "annotations",
+        }
+    }
+
+    /// The audit verb for changes to this field.
+    pub(crate) fn verb(&self) -> &'static str {
+        match self {
+            MetadataField::Labels => "label",
+            MetadataField::Annotations => "annotate",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MetadataUpdate {
+    pub(crate) field: MetadataField,
+    pub(crate) changes: BTreeMap<String, Option<String>>,
+    pub(crate) include_template: bool,
+}
+
+impl MetadataUpdate {
+    pub(crate) fn from_args(field: MetadataField, args: &HashMap<String, String>) -> Result<Self, String> {
+        let changes = args.get("changes").ok_or("No changes were provided")?;
+        let changes = serde_json::from_str(changes).map_err(|e| format!("Invalid changes: {}", e))?;
+        Ok(MetadataUpdate {
+            field,
+            changes,
+            include_template: args.get("include_template").map(|v| v == "true").unwrap_or(false),
+        })
+    }
+}
+
+impl KubeClientManager {
+    pub fn set_metadata(&self, window: &Window, ns: &str, kind: &str, name: &str, update: MetadataUpdate, cmd: &str) {
+        let result = self._set_metadata(window, ns, kind, name, &update, cmd);
+        if let Err(err) = result {
+            let field = update.field.key();
+            error!("Failed to update {} on {}: {}", field, name, err);
+            send_error(window, &format!("Failed to update {}. Reason: {}", field, err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _set_metadata(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        update: &MetadataUpdate,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        validate_changes(update.field, &update.changes)?;
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let discovery = Discovery::new(client.clone()).run().await?;
+                let (api, _ar, _caps) = api_for_kind(client, &discovery, kind, ns)?;
+                let include_template = update.include_template && WorkloadKind::from_kind(kind).is_some();
+                let patch = metadata_patch(update.field, &update.changes, include_template);
+                let before = api.get(name).await?;
+                let result = api.patch(name, &PatchParams::default(), &Patch::Merge(patch)).await;
+                let mutation = Mutation::for_object(update.field.verb(), &before, ns).before(&before);
+                self.audit(mutation, &result);
+                let updated = result?;
+                let current = match update.field {
+                    MetadataField::Labels => updated.labels().clone(),
+                    MetadataField::Annotations => updated.annotations().clone(),
+                };
+                info!("Updated {} on {} {}", update.field.key(), kind, name);
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&current).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to update metadata. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Builds a JSON merge patch for the changes. A `None` value becomes `null`, which removes the key.
+pub(crate) fn metadata_patch(
+    field: MetadataField,
+    changes: &BTreeMap<String, Option<String>>,
+    include_template: bool,
+) -> Value {
+    let mut patch = json!({ "metadata": { field.key(): changes } });
+    if include_template {
+        patch["spec"] = json!({ "template": { "metadata": { field.key(): changes } } });
+    }
+    patch
+}
+
+pub(crate) fn validate_changes(
+    field: MetadataField,
+    changes: &BTreeMap<String, Option<String>>,
+) -> Result<(), String> {
+    if changes.is_empty() {
+        return Err("No changes were provided".to_string());
+    }
+    let mut annotations_size = 0;
+    for (key, value) in changes {
+        validate_key(key)?;
+        if let Some(value) = value {
+            match field {
+                MetadataField::Labels => validate_label_value(key, value)?,
+                MetadataField::Annotations => annotations_size += key.len() + value.len(),
+            }
+        }
+    }
+    if annotations_size > MAX_ANNOTATIONS_SIZE {
+        return Err(format!("Annotations must not exceed {} bytes in total", MAX_ANNOTATIONS_SIZE));
+    }
+    Ok(())
+}
+
+/// Checks a label or annotation key: an optional DNS subdomain prefix followed by `/` and
+/// a name of at most 63 alphanumeric, `-`, `_` or `.` characters.
+pub(crate) fn validate_key(key: &str) -> Result<(), String> {
+    let (prefix, name) = match key.rsplit_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+    if let Some(prefix) = prefix {
+        if prefix.is_empty() || prefix.len() > MAX_PREFIX_LENGTH {
+            return Err(format!("Key '{}': prefix must be between 1 and {} characters", key, MAX_PREFIX_LENGTH));
+        }
+        if !DNS_SUBDOMAIN_PATTERN.is_match(prefix) {
+            return Err(format!("Key '{}': prefix must be a lowercase DNS subdomain", key));
+        }
+    }
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Key '{}': name must be between 1 and {} characters", key, MAX_NAME_LENGTH));
+    }
+    if !NAME_PATTERN.is_match(name) {
+        return Err(format!(
+            "Key '{}': name must consist of alphanumerics, '-', '_' or '.', and start and end with an alphanumeric",
+            key
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_label_value(key: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Ok(());
+    }
+    if value.len() > MAX_NAME_LENGTH {
+        return Err(format!("Value of '{}' must be at most {} characters", key, MAX_NAME_LENGTH));
+    }
+    if !NAME_PATTERN.is_match(value) {
+        return Err(format!(
+            "Value of '{}' must consist of alphanumerics, '-', '_' or '.', and start and end with an alphanumeric",
+            key
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_keys() {
+        // Four 62 character labels: 251 characters with the dots
+        let labels = vec!["a".repeat(62); 4].join(".");
+        let cases = [
+            ("app".to_string(), true),
+            ("app.kubernetes.io/name".to_string(), true),
+            ("example.com/My_Key.v1".to_string(), true),
+            (format!("example.com/{}", "n".repeat(63)), true),
+            (format!("{}.a/name", labels), true),
+            (format!("example.com/{}", "n".repeat(64)), false),
+            (format!("{}.ab/name", labels), false),
+            ("/name".to_string(), false),
+            ("example.com/".to_string(), false),
+            ("Example.com/name".to_string(), false),
+            ("a/b/c".to_string(), false),
+            ("-app".to_string(), false),
+            ("app-".to_string(), false),
+            ("my app".to_string(), false),
+            (String::new(), false),
+        ];
+        for (key, valid) in cases.iter() {
+            assert_eq!(validate_key(key).is_ok(), *valid, "{}", key);
+        }
+    }
+
+    #[test]
+    fn validates_label_values() {
+        let cases = [
+            ("".to_string(), true),
+            ("v1.2.3".to_string(), true),
+            ("A_b-C".to_string(), true),
+            ("x".repeat(63), true),
+            ("x".repeat(64), false),
+            ("-v1".to_string(), false),
+            ("v1.".to_string(), false),
+            ("a/b".to_string(), false),
+            ("two words".to_string(), false),
+        ];
+        for (value, valid) in cases.iter() {
+            assert_eq!(validate_label_value("app", value).is_ok(), *valid, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn removes_keys_with_null_in_the_patch() {
+        let changes: BTreeMap<String, Option<String>> =
+            [("tier".to_string(), Some("web".to_string())), ("old".to_string(), None)].into_iter().collect();
+        let cases = [
+            (MetadataField::Labels, false, json!({ "metadata": { "labels": { "tier": "web", "old": null } } })),
+            (
+                MetadataField::Annotations,
+                true,
+                json!({
+                    "metadata": { "annotations": { "tier": "web", "old": null } },
+                    "spec": { "template": { "metadata": { "annotations": { "tier": "web", "old": null } } } },
+                }),
+            ),
+        ];
+        for (field, include_template, expected) in cases.iter() {
+            assert_eq!(metadata_patch(*field, &changes, *include_template), *expected, "{:?}", field);
+        }
+    }
+
+    #[test]
+    fn checks_the_total_annotations_size() {
+        let big = |size: usize| -> BTreeMap<String, Option<String>> {
+            [("note".to_string(), Some("x".repeat(size))), ("gone".to_string(), None)].into_iter().collect()
+        };
+        assert!(validate_changes(MetadataField::Annotations, &big(MAX_ANNOTATIONS_SIZE - 4)).is_ok());
+        assert!(validate_changes(MetadataField::Annotations, &big(MAX_ANNOTATIONS_SIZE - 3)).is_err());
+        assert!(validate_changes(MetadataField::Labels, &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, ListParams, ResourceExt};
+use tauri::Window;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::export::expand_home;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::logs::{pod_containers, LogOptions};
+use crate::utils::send_error;
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogExportFormat {
+    Text,
+    /// One JSON object per line with the pod, container and timestamp.
+    JsonLines,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogExportRequest {
+    pub(crate) ns: String,
+    pub(crate) pod: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) selector: Option<String>,
+    /// The container and start of the range. Timestamps are on unless turned off.
+    pub(crate) options: LogOptions,
+    /// Lines written after this are left out.
+    pub(crate) until: Option<DateTime<Utc>>,
+    pub(crate) include_previous: bool,
+    pub(crate) path: PathBuf,
+    pub(crate) format: LogExportFormat,
+    pub(crate) gzip: bool,
+    /// An existing file at the path is only replaced when this is set.
+    pub(crate) overwrite: bool,
+}
+
+impl LogExportRequest {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let text = |key: &str| args.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let ns = text("ns").ok_or("A namespace is required")?;
+        let path = text("path").ok_or("An export path is required")?;
+        let format = match text("format").as_deref() {
+            None | Some("text") => LogExportFormat::Text,
+            Some("jsonl") | Some("json") => LogExportFormat::JsonLines,
+            Some(other) => return Err(format!("Unknown log export format {}", other)),
+        };
+        let until = match text("until") {
+            Some(value) => Some(
+                DateTime::parse_from_rfc3339(&value)
+                    .map_err(|e| format!("Invalid until {}: {}", value, e))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        let mut options = LogOptions::from_args(args, None)?;
+        options.timestamps = args.get("timestamps").map(|v| v != "false").unwrap_or(true);
+        if let (Some(since), Some(until)) = (options.since_time, until) {
+            if until < since {
+                return Err("The end of the range is before its start".to_string());
+            }
+        }
+        let mut request = LogExportRequest {
+            ns,
+            pod: text("pod"),
+            kind: text("kind"),
+            name: text("name"),
+            selector: text("selector"),
+            options,
+            until,
+            include_previous: args.get("previous").map(|v| v == "true").unwrap_or(false),
+            path: expand_home(&path),
+            format,
+            gzip: args.get("gzip").map(|v| v == "true").unwrap_or(false) || path.ends_with(".gz"),
+            overwrite: args.get("overwrite").map(|v| v == "true").unwrap_or(false),
+        };
+        // Previous instances are exported next to the current ones, never instead of them
+        request.options.previous = false;
+        if request.pod.is_none() && request.selector.is_none() && (request.kind.is_none() || request.name.is_none()) {
+            return Err("A pod, a workload kind and name, or a label selector, is required".to_string());
+        }
+        if request.path.is_dir() {
+            request.path = request.path.join(request.file_name());
+        }
+        if request.path.exists() && !request.overwrite {
+            return Err(format!("{} already exists. Choose another path or allow overwriting it", request.path.display()));
+        }
+        Ok(request)
+    }
+
+    /// The name used when the export path is a directory.
+    fn file_name(&self) -> String {
+        let source = self
+            .pod
+            .clone()
+            .or_else(|| self.name.clone())
+            .unwrap_or_else(|| "logs".to_string());
+        let extension = match self.format {
+            LogExportFormat::Text => "log",
+            LogExportFormat::JsonLines => "jsonl",
+        };
+        format!(
+            "{}-{}.{}{}",
+            source,
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            extension,
+            if self.gzip { ".gz" } else { "" }
+        )
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct ExportedStream {
+    pub(crate) pod: String,
+    pub(crate) container: String,
+    pub(crate) previous: bool,
+    pub(crate) lines: usize,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct LogExportReport {
+    pub(crate) path: String,
+    pub(crate) lines: usize,
+    pub(crate) streams: Vec<ExportedStream>,
+    pub(crate) warnings: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonLogLine<'a> {
+    pod: &'a str,
+    container: &'a str,
+    previous: bool,
+    timestamp: Option<&'a str>,
+    line: &'a str,
+}
+
+/// The export file, compressed or not. Lines go straight through a buffered writer.
+enum LogFileWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl LogFileWriter {
+    /// Refuses to replace an existing file unless `overwrite` is set, as the check when the
+    /// request was read can be outrun by another export to the same path.
+    fn create(path: &Path, gzip: bool, overwrite: bool) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if overwrite {
+            options.create(true).truncate(true);
+        } else {
+            options.create_new(true);
+        }
+        let file = BufWriter::new(options.open(path)?);
+        Ok(if gzip {
+            LogFileWriter::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            LogFileWriter::Plain(file)
+        })
+    }
+
+    /// Writes the gzip trailer and flushes what is left.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            LogFileWriter::Plain(mut file) => file.flush(),
+            LogFileWriter::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            LogFileWriter::Plain(file) => file.write(buf),
+            LogFileWriter::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            LogFileWriter::Plain(file) => file.flush(),
+            LogFileWriter::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// The container instance being exported, used to label its lines.
+struct ExportSource<'a> {
+    pod: &'a str,
+    container: &'a str,
+    previous: bool,
+    /// Text lines are prefixed with the source when more than one container is exported.
+    prefix: bool,
+}
+
+impl KubeClientManager {
+    pub fn export_logs(&self, window: &Window, request: LogExportRequest, cmd: &str) {
+        let result = self._export_logs(window, &request, cmd);
+        if let Err(err) = result {
+            error!("Failed to export logs to {}: {}", request.path.display(), err);
+            send_error(window, &format!("Failed to export logs. Reason: {}", err));
+        }
+    }
+
+    /// Writes the logs of a pod, or of every pod of a workload, one container after the other.
+    /// A container's previous instance comes before its current one.
+    #[tokio::main]
+    async fn _export_logs(&self, window: &Window, request: &LogExportRequest, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = match self.init_client().await {
+            Some(client) => client,
+            None => {
+                send_error(window, "Failed to export logs. Reason Kubeclient failed.");
+                return Ok(());
+            }
+        };
+        let pods: Api<Pod> = self.get_api(client.clone(), &request.ns);
+        let targets = match &request.pod {
+            Some(pod) => vec![pods.get(pod).await?],
+            None => {
+                let selector = match &request.selector {
+                    Some(selector) => selector.clone(),
+                    None => {
+                        let kind = request.kind.clone().unwrap_or_default();
+                        let name = request.name.clone().unwrap_or_default();
+                        self.workload_selector(client, &request.ns, &kind, &name).await?
+                    }
+                };
+                pods.list(&ListParams::default().labels(&selector)).await?.items
+            }
+        };
+        if targets.is_empty() {
+            return Err("No pods found to export logs from".into());
+        }
+        let prefix = request.pod.is_none() || request.options.container.is_none();
+        let mut writer = LogFileWriter::create(&request.path, request.gzip, request.overwrite)?;
+        let mut report = LogExportReport {
+            path: request.path.display().to_string(),
+            ..LogExportReport::default()
+        };
+        for pod in &targets {
+            let pod_name = pod.name_any();
+            for container in pod_containers(pod) {
+                if request.options.container.as_ref().map(|c| *c != container.name).unwrap_or(false) {
+                    continue;
+                }
+                let mut instances = Vec::new();
+                if request.include_previous && container.has_previous {
+                    instances.push(true);
+                }
+                if container.state == "running" || container.state == "terminated" {
+                    instances.push(false);
+                }
+                for previous in instances {
+                    let source = ExportSource { pod: &pod_name, container: &container.name, previous, prefix };
+                    match _export_stream(&pods, request, &source, &mut writer).await {
+                        Ok(lines) => {
+                            report.lines += lines;
+                            report.streams.push(ExportedStream {
+                                pod: pod_name.clone(),
+                                container: container.name.clone(),
+                                previous,
+                                lines,
+                            });
+                        },
+                        Err(err) => report.warnings.push(format!("{}/{}: {}", pod_name, container.name, err)),
+                    }
+                }
+            }
+        }
+        writer.finish()?;
+        info!("Exported {} log lines to {}", report.lines, report.path);
+        dispatch_to_frontend(window, cmd, serde_json::to_string(&report).unwrap());
+        Ok(())
+    }
+}
+
+/// Copies one container log into the writer line by line and returns how many lines were kept.
+async fn _export_stream(
+    pods: &Api<Pod>,
+    request: &LogExportRequest,
+    source: &ExportSource<'_>,
+    writer: &mut LogFileWriter,
+) -> Result<usize, Box<dyn Error>> {
+    let mut options = request.options.clone();
+    options.container = Some(source.container.to_string());
+    options.previous = source.previous;
+    // Always asked for, so lines can be checked against the range
+    options.timestamps = true;
+    let mut chunks = pods.log_stream(source.pod, &options.params(false)).await?.boxed();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut lines = 0;
+    'read: while let Some(chunk) = chunks.try_next().await? {
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let rest = pending.split_off(end + 1);
+            let line = String::from_utf8_lossy(&pending[..end]).trim_end_matches('\r').to_string();
+            pending = rest;
+            match _write_line(writer, request, source, &line)? {
+                Some(true) => lines += 1,
+                Some(false) => {},
+                None => break 'read,
+            }
+        }
+    }
+    if !pending.is_empty() {
+        if let Some(true) = _write_line(writer, request, source, &String::from_utf8_lossy(&pending))? {
+            lines += 1;
+        }
+    }
+    Ok(lines)
+}
+
+/// Returns whether the line was written, or None once the end of the range is passed.
+fn _write_line(
+    writer: &mut LogFileWriter,
+    request: &LogExportRequest,
+    source: &ExportSource,
+    line: &str,
+) -> io::Result<Option<bool>> {
+    let (timestamp, message) = match line.split_once(' ') {
+        Some((ts, rest)) if DateTime::parse_from_rfc3339(ts).is_ok() => (Some(ts), rest),
+        _ => (None, line),
+    };
+    if let Some(at) = timestamp.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok()) {
+        let at = at.with_timezone(&Utc);
+        // The API server rounds the start to whole seconds
+        if request.options.since_time.map(|since| at < since).unwrap_or(false) {
+            return Ok(Some(false));
+        }
+        if request.until.map(|until| at > until).unwrap_or(false) {
+            return Ok(None);
+        }
+    }
+    match request.format {
+        LogExportFormat::JsonLines => {
+            let record = JsonLogLine {
+                pod: source.pod,
+                container: source.container,
+                previous: source.previous,
+                timestamp,
+                line: message,
+            };
+            writeln!(writer, "{}", serde_json::to_string(&record).unwrap())?;
+        },
+        LogExportFormat::Text => {
+            if source.prefix {
+                let previous = if source.previous { " (previous)" } else { "" };
+                write!(writer, "[{}/{}{}] ", source.pod, source.container, previous)?;
+            }
+            match timestamp {
+                Some(ts) if request.options.timestamps => writeln!(writer, "{} {}", ts, message)?,
+                _ => writeln!(writer, "{}", message)?,
+            }
+        },
+    }
+    Ok(Some(true))
+}
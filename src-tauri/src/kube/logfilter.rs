@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use regex::{Regex, RegexBuilder};
+use crate::kube::logparse::{parse_field_filters, parse_field_list, FieldFilter, ParsedRecord};
+
+/// Messages on a log stream's control channel that start with this prefix carry a new filter.
+/// Any other message stops the stream.
+pub const FILTER_CONTROL_PREFIX: &str = "FILTER ";
+
+/// Include and exclude patterns applied to log lines before they are sent to the frontend, and
+/// conditions on the fields of structured lines.
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+    fields: Vec<FieldFilter>,
+}
+
+/// A filter change for running streams. Without a stream id it applies to every log stream.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct FilterUpdate {
+    #[serde(default)]
+    pub(crate) stream: Option<String>,
+    #[serde(default)]
+    pub(crate) include: Option<String>,
+    #[serde(default)]
+    pub(crate) exclude: Option<String>,
+    #[serde(default)]
+    pub(crate) ignore_case: bool,
+    /// Field conditions such as `level>=warn` or `trace_id=abc`. All of them must hold.
+    #[serde(default)]
+    pub(crate) fields: Vec<String>,
+}
+
+impl FilterUpdate {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let text = |key: &str| args.get(key).cloned().filter(|v| !v.is_empty());
+        Ok(FilterUpdate {
+            stream: text("stream"),
+            include: text("include"),
+            exclude: text("exclude"),
+            ignore_case: args.get("ignore_case").map(|v| v == "true").unwrap_or(false),
+            fields: parse_field_list(args.get("fields"))?,
+        })
+    }
+
+    pub(crate) fn filter(&self) -> Result<LogFilter, String> {
+        LogFilter::new(self.include.as_deref(), self.exclude.as_deref(), self.ignore_case, &self.fields)
+    }
+
+    /// The message to send on the control channel of running streams.
+    pub(crate) fn control_message(&self) -> String {
+        format!("{}{}", FILTER_CONTROL_PREFIX, serde_json::to_string(self).unwrap())
+    }
+}
+
+/// What a stream should do after reading its control channel.
+pub(crate) enum TailControl {
+    Continue,
+    Stop,
+}
+
+impl LogFilter {
+    pub(crate) fn new(
+        include: Option<&str>,
+        exclude: Option<&str>,
+        ignore_case: bool,
+        fields: &[String],
+    ) -> Result<Self, String> {
+        let build = |pattern: Option<&str>, name: &str| -> Result<Option<Regex>, String> {
+            match pattern.filter(|p| !p.is_empty()) {
+                Some(pattern) => RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .map(Some)
+                    .map_err(|e| format!("Invalid {} pattern: {}", name, e)),
+                None => Ok(None),
+            }
+        };
+        Ok(LogFilter {
+            include: build(include, "include")?,
+            exclude: build(exclude, "exclude")?,
+            fields: parse_field_filters(fields)?,
+        })
+    }
+
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        FilterUpdate::from_args(args)?.filter()
+    }
+
+    /// Returns None when the line is filtered out. A kept line comes with the spans the include
+    /// pattern matched, as UTF-16 offsets so the webview can slice the string directly.
+    pub(crate) fn apply(&self, line: &str) -> Option<Vec<[usize; 2]>> {
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(line) {
+                return None;
+            }
+        }
+        let include = match &self.include {
+            Some(include) => include,
+            None => return Some(Vec::new()),
+        };
+        let mut matches = Vec::new();
+        let (mut byte, mut unit) = (0, 0);
+        for found in include.find_iter(line) {
+            if found.start() == found.end() {
+                continue;
+            }
+            unit += line[byte..found.start()].encode_utf16().count();
+            let start = unit;
+            unit += found.as_str().encode_utf16().count();
+            byte = found.end();
+            matches.push([start, unit]);
+        }
+        if matches.is_empty() && !include.is_match(line) {
+            return None;
+        }
+        Some(matches)
+    }
+
+    /// Checks the field conditions against a parsed line. A line without the field only passes
+    /// `!=` and `!~` conditions.
+    pub(crate) fn accepts_record(&self, record: Option<&ParsedRecord>) -> bool {
+        self.fields.iter().all(|f| f.accepts(record))
+    }
+
+    /// Drains the control channel of a stream, applying filter updates meant for it.
+    pub(crate) fn poll_control(&mut self, rx: &Receiver<String>, stream_id: &str) -> TailControl {
+        while let Ok(message) = rx.try_recv() {
+            match message.strip_prefix(FILTER_CONTROL_PREFIX) {
+                Some(update) => {
+                    let update: FilterUpdate = match serde_json::from_str(update) {
+                        Ok(update) => update,
+                        Err(err) => {
+                            warn!("Ignoring malformed filter update: {}", err);
+                            continue;
+                        }
+                    };
+                    if update.stream.as_deref().map(|s| s != stream_id).unwrap_or(false) {
+                        continue;
+                    }
+                    match update.filter() {
+                        Ok(filter) => {
+                            debug!("Updated log filter of {}", stream_id);
+                            *self = filter;
+                        },
+                        Err(err) => warn!("Keeping log filter of {}: {}", stream_id, err),
+                    }
+                },
+                None => {
+                    debug!("Work is done: {:?}", message);
+                    return TailControl::Stop;
+                }
+            }
+        }
+        TailControl::Continue
+    }
+}
@@ -0,0 +1,418 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use chrono::DateTime;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+const LEVEL_KEYS: [&str; 6] = ["level", "lvl", "severity", "loglevel", "levelname", "log.level"];
+const TIME_KEYS: [&str; 5] = ["ts", "time", "timestamp", "@timestamp", "t"];
+const MESSAGE_KEYS: [&str; 4] = ["msg", "message", "@message", "log"];
+/// Levels from least to most severe.
+const LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "fatal"];
+
+const TEXT_LEVEL_PATTERN: &str = r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL|PANIC|CRITICAL)\b";
+const LOGFMT_PAIR_PATTERN: &str = r#"([A-Za-z_@][\w.\-/@]*)=("(?:[^"\\]|\\.)*"|\S*)"#;
+const FIELD_FILTER_PATTERN: &str = r"^\s*([\w.@\-/]+)\s*(>=|<=|!=|!~|=|>|<|~)\s*(.*?)\s*$";
+
+/// A log line broken into fields. Level, timestamp and message are taken from whichever of the
+/// usual keys the line uses, so filters and the UI do not need to know the logger.
+#[derive(Clone, serde::Serialize, Default, Debug)]
+pub struct ParsedRecord {
+    /// One of json, logfmt or text. Text records only carry a level found in the line.
+    pub(crate) format: String,
+    pub(crate) level: Option<String>,
+    pub(crate) ts: Option<String>,
+    pub(crate) message: Option<String>,
+    /// Every field, with nested JSON keys joined by dots.
+    pub(crate) fields: BTreeMap<String, String>,
+}
+
+impl ParsedRecord {
+    fn from_fields(format: &str, fields: BTreeMap<String, String>) -> Self {
+        let first = |keys: &[&str]| keys.iter().find_map(|k| fields.get(*k)).cloned();
+        ParsedRecord {
+            format: format.to_string(),
+            level: first(&LEVEL_KEYS).and_then(|l| normalize_level(&l)),
+            ts: first(&TIME_KEYS),
+            message: first(&MESSAGE_KEYS),
+            fields,
+        }
+    }
+
+    /// Looks up a field by name. `level`, `ts` and `msg` resolve to the normalised values.
+    pub(crate) fn get(&self, field: &str) -> Option<&String> {
+        match field {
+            "level" => self.level.as_ref(),
+            "ts" | "time" | "timestamp" => self.ts.as_ref(),
+            "msg" | "message" => self.message.as_ref(),
+            _ => self.fields.get(field),
+        }
+    }
+}
+
+/// Detects JSON and logfmt lines. The patterns are compiled once per stream.
+pub(crate) struct LogParser {
+    text_level: Regex,
+    logfmt_pair: Regex,
+}
+
+impl LogParser {
+    pub(crate) fn new() -> Self {
+        LogParser {
+            text_level: Regex::new(TEXT_LEVEL_PATTERN).unwrap(),
+            logfmt_pair: Regex::new(LOGFMT_PAIR_PATTERN).unwrap(),
+        }
+    }
+
+    /// Parses JSON and logfmt lines. Other lines yield a text record when a level can be spotted.
+    /// A timestamp added by the API server in front of the line is used when the record has none.
+    pub(crate) fn parse(&self, line: &str) -> Option<ParsedRecord> {
+        let mut parts = line.splitn(2, ' ');
+        let (first, rest) = (parts.next().unwrap_or_default(), parts.next());
+        if let (Ok(_), Some(rest)) = (DateTime::parse_from_rfc3339(first), rest) {
+            let mut record = self.parse_record(rest)?;
+            if record.ts.is_none() {
+                record.ts = Some(first.to_string());
+            }
+            return Some(record);
+        }
+        self.parse_record(line)
+    }
+
+    fn parse_record(&self, line: &str) -> Option<ParsedRecord> {
+        let trimmed = line.trim();
+        if trimmed.starts_with('{') {
+            if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(trimmed) {
+                let mut fields = BTreeMap::new();
+                _flatten("", &object, &mut fields);
+                return Some(ParsedRecord::from_fields("json", fields));
+            }
+        }
+        if let Some(fields) = self.parse_logfmt(trimmed) {
+            return Some(ParsedRecord::from_fields("logfmt", fields));
+        }
+        self.text_level.find(line).map(|level| ParsedRecord {
+            format: "text".to_string(),
+            level: normalize_level(level.as_str()),
+            ..ParsedRecord::default()
+        })
+    }
+
+    /// Accepts a line as logfmt when it is made of at least two `key=value` pairs and nothing
+    /// else but whitespace.
+    fn parse_logfmt(&self, line: &str) -> Option<BTreeMap<String, String>> {
+        if !line.contains('=') || !self.logfmt_pair.replace_all(line, "").trim().is_empty() {
+            return None;
+        }
+        let mut fields = BTreeMap::new();
+        for pair in self.logfmt_pair.captures_iter(line) {
+            let value = &pair[2];
+            let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                _unquote(&value[1..value.len() - 1])
+            } else {
+                value.to_string()
+            };
+            fields.insert(pair[1].to_string(), value);
+        }
+        if fields.len() >= 2 {
+            Some(fields)
+        } else {
+            None
+        }
+    }
+}
+
+/// Resolves the escapes of a quoted logfmt value in a single pass, so an escaped backslash is
+/// never read as the start of another escape.
+fn _unquote(quoted: &str) -> String {
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(escaped) => value.push(escaped),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+pub(crate) fn normalize_level(level: &str) -> Option<String> {
+    let level = level.trim().to_lowercase();
+    let normalized = match level.as_str() {
+        "trace" | "trc" | "10" => "trace",
+        "debug" | "dbg" | "d" | "20" => "debug",
+        "info" | "inf" | "i" | "information" | "notice" | "30" => "info",
+        "warn" | "warning" | "wrn" | "w" | "40" => "warn",
+        "error" | "err" | "e" | "50" => "error",
+        "fatal" | "panic" | "critical" | "crit" | "emergency" | "alert" | "dpanic" | "60" => "fatal",
+        _ => return None,
+    };
+    Some(normalized.to_string())
+}
+
+fn _level_rank(level: &str) -> Option<usize> {
+    let level = normalize_level(level)?;
+    LEVELS.iter().position(|l| *l == level)
+}
+
+fn _flatten(prefix: &str, object: &Map<String, Value>, fields: &mut BTreeMap<String, String>) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Object(nested) => _flatten(&key, nested, fields),
+            Value::String(text) => {
+                fields.insert(key, text.clone());
+            },
+            Value::Null => {},
+            other => {
+                fields.insert(key, other.to_string());
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FieldOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Matches,
+    NotMatches,
+}
+
+/// A condition on a parsed field, such as `level>=warn`, `trace_id=abc` or `msg~timeout`.
+#[derive(Clone, Debug)]
+pub struct FieldFilter {
+    field: String,
+    op: FieldOp,
+    value: String,
+    pattern: Option<Regex>,
+}
+
+impl FieldFilter {
+    pub(crate) fn parse(expression: &str) -> Result<Self, String> {
+        let caps = Regex::new(FIELD_FILTER_PATTERN)
+            .unwrap()
+            .captures(expression)
+            .ok_or_else(|| format!("Invalid field filter '{}'. Use field=value, field>=value or field~pattern", expression))?;
+        let op = match &caps[2] {
+            "=" => FieldOp::Eq,
+            "!=" => FieldOp::Ne,
+            ">" => FieldOp::Gt,
+            ">=" => FieldOp::Ge,
+            "<" => FieldOp::Lt,
+            "<=" => FieldOp::Le,
+            "~" => FieldOp::Matches,
+            _ => FieldOp::NotMatches,
+        };
+        let value = caps[3].trim_matches('"').to_string();
+        let pattern = match op {
+            FieldOp::Matches | FieldOp::NotMatches => {
+                Some(Regex::new(&value).map_err(|e| format!("Invalid pattern in '{}': {}", expression, e))?)
+            },
+            _ => None,
+        };
+        Ok(FieldFilter { field: caps[1].to_string(), op, value, pattern })
+    }
+
+    /// Lines without the field only pass negative conditions.
+    pub(crate) fn accepts(&self, record: Option<&ParsedRecord>) -> bool {
+        let actual = match record.and_then(|r| r.get(&self.field)) {
+            Some(actual) => actual,
+            None => return matches!(self.op, FieldOp::Ne | FieldOp::NotMatches),
+        };
+        if let Some(pattern) = &self.pattern {
+            return pattern.is_match(actual) == (self.op == FieldOp::Matches);
+        }
+        let ordering = self.compare(actual);
+        match self.op {
+            FieldOp::Eq => ordering == Some(Ordering::Equal),
+            FieldOp::Ne => ordering != Some(Ordering::Equal),
+            FieldOp::Gt => ordering == Some(Ordering::Greater),
+            FieldOp::Ge => matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal)),
+            FieldOp::Lt => ordering == Some(Ordering::Less),
+            FieldOp::Le => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+            _ => true,
+        }
+    }
+
+    /// Levels compare by severity and numbers numerically. Anything else compares as text,
+    /// with equality ignoring case.
+    fn compare(&self, actual: &str) -> Option<Ordering> {
+        if self.field == "level" {
+            return Some(_level_rank(actual)?.cmp(&_level_rank(&self.value)?));
+        }
+        if let (Ok(actual), Ok(expected)) = (actual.parse::<f64>(), self.value.parse::<f64>()) {
+            return actual.partial_cmp(&expected);
+        }
+        if actual.eq_ignore_ascii_case(&self.value) {
+            return Some(Ordering::Equal);
+        }
+        Some(actual.cmp(self.value.as_str()))
+    }
+}
+
+/// Reads the `fields` argument: a JSON array of conditions, or one condition per line. Commas
+/// are not separators since they can appear in values and patterns.
+pub(crate) fn parse_field_list(fields: Option<&String>) -> Result<Vec<String>, String> {
+    match fields.map(|f| f.trim()) {
+        None | Some("") => Ok(Vec::new()),
+        Some(fields) if fields.starts_with('[') => serde_json::from_str(fields)
+            .map_err(|e| format!("Invalid field filters: expected a JSON array of strings: {}", e)),
+        Some(fields) => Ok(fields
+            .lines()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .collect()),
+    }
+}
+
+pub(crate) fn parse_field_filters(filters: &[String]) -> Result<Vec<FieldFilter>, String> {
+    filters.iter().filter(|f| !f.trim().is_empty()).map(|f| FieldFilter::parse(f)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(expression: &str, record: Option<&ParsedRecord>) -> bool {
+        FieldFilter::parse(expression).unwrap().accepts(record)
+    }
+
+    #[test]
+    fn flattens_nested_json() {
+        let parser = LogParser::new();
+        let record = parser
+            .parse(r#"{"level":"WARN","msg":"disk full","ctx":{"node":"a","retry":{"count":3}},"ok":true,"tags":[1,2],"gone":null}"#)
+            .unwrap();
+        assert_eq!(record.format, "json");
+        assert_eq!(record.level.as_deref(), Some("warn"));
+        assert_eq!(record.message.as_deref(), Some("disk full"));
+        let cases = [
+            ("ctx.node", Some("a")),
+            ("ctx.retry.count", Some("3")),
+            ("ok", Some("true")),
+            ("tags", Some("[1,2]")),
+            ("gone", None),
+            ("ctx", None),
+        ];
+        for (field, expected) in cases.iter() {
+            assert_eq!(record.fields.get(*field).map(|v| v.as_str()), *expected, "field {}", field);
+        }
+    }
+
+    #[test]
+    fn takes_the_server_timestamp_when_the_record_has_none() {
+        let parser = LogParser::new();
+        let record = parser.parse(r#"2024-03-01T10:00:00.5Z {"msg":"started"}"#).unwrap();
+        assert_eq!(record.ts.as_deref(), Some("2024-03-01T10:00:00.5Z"));
+        let record = parser.parse(r#"2024-03-01T10:00:00.5Z {"msg":"started","time":"09:59"}"#).unwrap();
+        assert_eq!(record.ts.as_deref(), Some("09:59"));
+    }
+
+    #[test]
+    fn parses_logfmt_quoting_and_escapes() {
+        let parser = LogParser::new();
+        let cases: [(&str, &[(&str, &str)]); 5] = [
+            (r#"level=info msg="hello world" user=bob"#, &[("msg", "hello world"), ("user", "bob")]),
+            (r#"msg="say \"hi\"" level=debug"#, &[("msg", r#"say "hi""#)]),
+            (r#"path="C:\\tmp\\" level=debug"#, &[("path", r"C:\tmp\")]),
+            (r#"msg="line\nnext\tend" level=debug"#, &[("msg", "line\nnext\tend")]),
+            ("empty= level=warn", &[("empty", ""), ("level", "warn")]),
+        ];
+        for (line, expected) in cases.iter() {
+            let record = parser.parse(line).unwrap_or_else(|| panic!("{} was not parsed", line));
+            assert_eq!(record.format, "logfmt", "{}", line);
+            for (field, value) in expected.iter() {
+                assert_eq!(record.fields.get(*field).map(|v| v.as_str()), Some(*value), "{} in {}", field, line);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_lines_that_are_not_logfmt() {
+        let parser = LogParser::new();
+        for line in ["key=value", "GET /index status=200 took 3ms", "plain text"].iter() {
+            assert!(parser.parse(line).is_none(), "{}", line);
+        }
+        let record = parser.parse("2024-03-01T10:00:00Z ERROR failed to connect").unwrap();
+        assert_eq!(record.format, "text");
+        assert_eq!(record.level.as_deref(), Some("error"));
+    }
+
+    #[test]
+    fn normalizes_levels() {
+        let cases = [
+            ("WARNING", Some("warn")),
+            ("Err", Some("error")),
+            ("50", Some("error")),
+            (" info ", Some("info")),
+            ("dpanic", Some("fatal")),
+            ("TRC", Some("trace")),
+            ("verbose", None),
+        ];
+        for (level, expected) in cases.iter() {
+            assert_eq!(normalize_level(level).as_deref(), *expected, "{}", level);
+        }
+    }
+
+    #[test]
+    fn compares_fields() {
+        let parser = LogParser::new();
+        let record = parser
+            .parse(r#"{"level":"error","status":"503","latency":"9.5","user":"Bob","msg":"request timeout"}"#)
+            .unwrap();
+        let cases = [
+            ("level>=warn", true),
+            ("level>=fatal", false),
+            ("level<info", false),
+            ("level=ERROR", true),
+            // Numbers compare numerically, so 503 > 60 and 9.5 < 10 even though the text sorts the other way
+            ("status>60", true),
+            ("status>=503", true),
+            ("latency<10", true),
+            ("user=bob", true),
+            ("user!=alice", true),
+            ("msg~time(out)?", true),
+            ("msg!~timeout", false),
+            ("trace_id!=abc", true),
+            ("trace_id!~abc", true),
+            ("trace_id=abc", false),
+            ("trace_id~.", false),
+        ];
+        for (expression, expected) in cases.iter() {
+            assert_eq!(filter(expression, Some(&record)), *expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn unparsed_lines_only_pass_negative_conditions() {
+        assert!(filter("level!=debug", None));
+        assert!(filter("msg!~panic", None));
+        assert!(!filter("level>=warn", None));
+        assert!(!filter("msg~panic", None));
+    }
+
+    #[test]
+    fn parses_field_lists() {
+        let json = r#"["level>=warn", "msg~a,b"]"#.to_string();
+        assert_eq!(parse_field_list(Some(&json)).unwrap(), vec!["level>=warn", "msg~a,b"]);
+        let lines = "level>=warn\n\n msg~a,b \n".to_string();
+        assert_eq!(parse_field_list(Some(&lines)).unwrap(), vec!["level>=warn", "msg~a,b"]);
+        assert!(parse_field_list(None).unwrap().is_empty());
+        let broken = "[\"level>=warn\"".to_string();
+        assert!(parse_field_list(Some(&broken)).unwrap_err().starts_with("Invalid field filters"));
+        assert!(FieldFilter::parse("level").is_err());
+        assert!(FieldFilter::parse("msg~(").is_err());
+    }
+}
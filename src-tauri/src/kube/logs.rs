@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::{ContainerState, ContainerStatus, Pod};
+use kube::api::{Api, LogParams, ResourceExt};
+use tauri::Window;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::store::logs::{self as log_store, LogSearchQuery};
+use crate::utils::send_error;
+
+const DEFAULT_CONTAINER_ANNOTATION: &str = "kubectl.kubernetes.io/default-container";
+
+/// What to read from a container log. `since_time` is turned into `since_seconds` when the
+/// request is made, as the pinned client has no separate field for it.
+#[derive(Clone, Debug, Default)]
+pub struct LogOptions {
+    pub(crate) container: Option<String>,
+    pub(crate) previous: bool,
+    pub(crate) timestamps: bool,
+    pub(crate) since_seconds: Option<i64>,
+    pub(crate) since_time: Option<DateTime<Utc>>,
+    pub(crate) tail_lines: Option<i64>,
+    pub(crate) limit_bytes: Option<i64>,
+}
+
+impl LogOptions {
+    /// Reads the options from command args. `default_tail` applies when neither a tail nor a
+    /// start time is given.
+    pub(crate) fn from_args(args: &HashMap<String, String>, default_tail: Option<i64>) -> Result<Self, String> {
+        let number = |key: &str| -> Result<Option<i64>, String> {
+            match args.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                Some(value) => match value.parse::<i64>() {
+                    Ok(n) if n >= 0 => Ok(Some(n)),
+                    _ => Err(format!("{} must be a positive number", key)),
+                },
+                None => Ok(None),
+            }
+        };
+        let since_time = match args.get("since_time").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            Some(value) => Some(
+                DateTime::parse_from_rfc3339(value)
+                    .map_err(|e| format!("Invalid since_time {}: {}", value, e))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        let since_seconds = number("since_seconds")?;
+        let mut tail_lines = number("tail_lines")?;
+        if tail_lines.is_none() && since_seconds.is_none() && since_time.is_none() {
+            tail_lines = default_tail;
+        }
+        Ok(LogOptions {
+            container: args.get("container").cloned().filter(|c| !c.is_empty()),
+            previous: args.get("previous").map(|v| v == "true").unwrap_or(false),
+            timestamps: args.get("timestamps").map(|v| v == "true").unwrap_or(false),
+            since_seconds,
+            since_time,
+            tail_lines,
+            limit_bytes: number("limit_bytes")?,
+        })
+    }
+
+    pub(crate) fn params(&self, follow: bool) -> LogParams {
+        let since_seconds = match self.since_time {
+            // Rounded up so the line written at since_time is included
+            Some(since) => Some((Utc::now() - since).num_seconds().max(0) + 1),
+            None => self.since_seconds,
+        };
+        LogParams {
+            container: self.container.clone(),
+            // The previous instance has exited, so there is nothing to follow
+            follow: follow && !self.previous,
+            previous: self.previous,
+            timestamps: self.timestamps,
+            since_seconds,
+            tail_lines: self.tail_lines,
+            limit_bytes: self.limit_bytes,
+            ..LogParams::default()
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct PodContainer {
+    pub(crate) name: String,
+    /// One of init, container or ephemeral.
+    pub(crate) kind: String,
+    pub(crate) image: String,
+    pub(crate) state: String,
+    pub(crate) reason: Option<String>,
+    pub(crate) ready: bool,
+    pub(crate) restart_count: i32,
+    /// True when a terminated previous instance is kept and its logs can be read.
+    pub(crate) has_previous: bool,
+    pub(crate) default: bool,
+}
+
+impl KubeClientManager {
+    pub fn get_pod_containers(&self, window: &Window, ns: &str, pod: &str, cmd: &str) {
+        let result = self._get_pod_containers(window, ns, pod, cmd);
+        if let Err(err) = result {
+            error!("Failed to get containers of {}: {}", pod, err);
+            send_error(window, &format!("Failed to get containers. Reason: {}", err));
+        }
+    }
+
+    /// Searches lines kept by log capture. They stay searchable after their pods are gone.
+    pub fn search_logs(&self, window: &Window, query: LogSearchQuery, cmd: &str) {
+        match log_store::search(&query) {
+            Ok(lines) => dispatch_to_frontend(window, cmd, serde_json::to_string(&lines).unwrap()),
+            Err(err) => {
+                error!("Failed to search logs: {}", err);
+                send_error(window, &format!("Failed to search logs. Reason: {}", err));
+            }
+        }
+    }
+
+    #[tokio::main]
+    async fn _get_pod_containers(&self, window: &Window, ns: &str, pod: &str, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let pods: Api<Pod> = self.get_api(client, ns);
+                let pod = pods.get(pod).await?;
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&pod_containers(&pod)).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to get containers. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Lists the init, regular and ephemeral containers of a pod with their current state.
+pub(crate) fn pod_containers(pod: &Pod) -> Vec<PodContainer> {
+    let default_name = pod
+        .annotations()
+        .get(DEFAULT_CONTAINER_ANNOTATION)
+        .cloned()
+        .or_else(|| pod.spec.as_ref().and_then(|s| s.containers.first()).map(|c| c.name.clone()));
+    let spec = pod.spec.clone().unwrap_or_default();
+    let status = pod.status.clone().unwrap_or_default();
+    let mut containers = Vec::new();
+    let mut push = |kind: &str, name: &str, image: Option<String>, statuses: &Option<Vec<ContainerStatus>>| {
+        let status = statuses.as_ref().and_then(|s| s.iter().find(|s| s.name == name));
+        let (state, reason) = _state(status.and_then(|s| s.state.as_ref()));
+        containers.push(PodContainer {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            image: image.unwrap_or_default(),
+            state,
+            reason,
+            ready: status.map(|s| s.ready).unwrap_or(false),
+            restart_count: status.map(|s| s.restart_count).unwrap_or(0),
+            has_previous: status
+                .and_then(|s| s.last_state.as_ref())
+                .map(|s| s.terminated.is_some())
+                .unwrap_or(false),
+            default: default_name.as_deref() == Some(name),
+        });
+    };
+    for container in spec.init_containers.unwrap_or_default() {
+        push("init", &container.name, container.image, &status.init_container_statuses);
+    }
+    for container in spec.containers {
+        push("container", &container.name, container.image, &status.container_statuses);
+    }
+    for container in spec.ephemeral_containers.unwrap_or_default() {
+        push("ephemeral", &container.name, container.image, &status.ephemeral_container_statuses);
+    }
+    containers
+}
+
+fn _state(state: Option<&ContainerState>) -> (String, Option<String>) {
+    match state {
+        Some(state) if state.running.is_some() => ("running".to_string(), None),
+        Some(state) if state.terminated.is_some() => (
+            "terminated".to_string(),
+            state.terminated.as_ref().and_then(|t| t.reason.clone()),
+        ),
+        Some(state) if state.waiting.is_some() => (
+            "waiting".to_string(),
+            state.waiting.as_ref().and_then(|w| w.reason.clone()),
+        ),
+        _ => ("unknown".to_string(), None),
+    }
+}
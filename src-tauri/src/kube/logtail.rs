@@ -0,0 +1,801 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::sync::mpsc::Receiver;
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, ListParams, LogParams, ResourceExt};
+use kube::runtime::watcher;
+use tauri::Window;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, Duration, Instant};
+use crate::kube::common::label_selector_string;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::logfilter::{LogFilter, TailControl};
+use crate::kube::logparse::{LogParser, ParsedRecord};
+use crate::kube::logs::{pod_containers, LogOptions};
+use crate::kube::Payload;
+use crate::store::logs::LogCapture;
+use crate::utils::send_error;
+
+pub const LOG_BATCH_CHANNEL: &str = "app::log_batch";
+/// How often a running tail flushes buffered lines and checks whether it was asked to stop.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+/// A full batch is sent early, but not more often than this.
+const MIN_FLUSH_INTERVAL: Duration = Duration::from_millis(25);
+const MAX_BATCH_LINES: usize = 500;
+/// Lines waiting beyond this are dropped, oldest first, and reported with a marker.
+const MAX_BUFFERED_LINES: usize = 5000;
+/// Wait before reconnecting a dropped log stream, doubled while the container is not back.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// A line from one container of a multi-pod tail.
+#[derive(Clone, serde::Serialize, Default, Debug)]
+pub struct LogLine {
+    pub(crate) pod: String,
+    pub(crate) container: String,
+    pub(crate) line: String,
+    /// Set on lines the tail adds itself: attached, detached, reconnected, container_restarted,
+    /// pod_replaced, ended or dropped.
+    pub(crate) marker: Option<String>,
+    /// Spans matched by the include filter, in UTF-16 offsets.
+    pub(crate) matches: Vec<[usize; 2]>,
+    /// The fields of a JSON or logfmt line, or the level spotted in a plain one.
+    pub(crate) parsed: Option<ParsedRecord>,
+    /// When the server logged the line, in milliseconds since the epoch.
+    pub(crate) ts: Option<i64>,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct LogBatch {
+    pub(crate) stream: String,
+    pub(crate) lines: Vec<LogLine>,
+    /// Lines dropped before this batch because the frontend was not keeping up.
+    pub(crate) dropped: usize,
+}
+
+impl LogLine {
+    pub(crate) fn marker(pod: &str, container: &str, marker: &str, line: String) -> Self {
+        LogLine {
+            pod: pod.to_string(),
+            container: container.to_string(),
+            line,
+            marker: Some(marker.to_string()),
+            ..LogLine::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkloadLogRequest {
+    pub(crate) ns: String,
+    pub(crate) kind: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) selector: Option<String>,
+    pub(crate) options: LogOptions,
+    pub(crate) filter: LogFilter,
+}
+
+impl WorkloadLogRequest {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let request = WorkloadLogRequest {
+            ns: args.get("ns").cloned().unwrap_or_default(),
+            kind: args.get("kind").cloned().filter(|k| !k.is_empty()),
+            name: args.get("name").cloned().filter(|n| !n.is_empty()),
+            selector: args.get("selector").cloned().filter(|s| !s.trim().is_empty()),
+            options: LogOptions::from_args(args, Some(10))?,
+            filter: LogFilter::from_args(args)?,
+        };
+        if request.selector.is_none() && (request.kind.is_none() || request.name.is_none()) {
+            return Err("A workload kind and name, or a label selector, is required".to_string());
+        }
+        Ok(request)
+    }
+
+    /// Identifies the tail in the events it sends.
+    pub(crate) fn stream_id(&self) -> String {
+        match (&self.kind, &self.name) {
+            (Some(kind), Some(name)) => format!("{}/{}/{}", self.ns, kind, name),
+            _ => format!("{}/{}", self.ns, self.selector.clone().unwrap_or_default()),
+        }
+    }
+}
+
+pub(crate) enum LogEvent {
+    Line(LogLine),
+    /// A container stream ended, because the container exited or the connection closed.
+    Ended { pod: String, container: String, error: Option<String> },
+}
+
+impl KubeClientManager {
+    pub fn tail_logs_for_workload(&self, window: Window, request: WorkloadLogRequest, rx: &Receiver<String>) {
+        let result = self._tail_logs_for_workload(&window, &request, rx);
+        if let Err(err) = result {
+            error!("Failed to tail logs for {}: {}", request.stream_id(), err);
+            send_error(&window, &format!("Failed to tail logs. Reason: {}", err));
+        }
+    }
+
+    /// Follows every pod matching the workload selector, like `stern`. Pods are found through a
+    /// watch, so replicas that start later are attached and deleted ones are detached. A new pod
+    /// that follows a deleted one is reported as its replacement.
+    #[tokio::main]
+    async fn _tail_logs_for_workload(
+        &self,
+        window: &Window,
+        request: &WorkloadLogRequest,
+        rx: &Receiver<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = match self.init_client().await {
+            Some(client) => client,
+            None => {
+                send_error(window, "Failed to tail logs. Reason Kubeclient failed.");
+                return Ok(());
+            }
+        };
+        let selector = match &request.selector {
+            Some(selector) => selector.clone(),
+            None => {
+                let kind = request.kind.clone().unwrap_or_default();
+                let name = request.name.clone().unwrap_or_default();
+                self.workload_selector(client.clone(), &request.ns, &kind, &name).await?
+            }
+        };
+        let stream_id = request.stream_id();
+        info!("Tailing logs for {} with selector {}", stream_id, selector);
+        let pods: Api<Pod> = self.get_api(client, &request.ns);
+        let mut events = watcher(pods.clone(), ListParams::default().labels(&selector)).boxed();
+        let (line_tx, mut line_rx) = unbounded_channel::<LogEvent>();
+        let mut followed = Followed::default();
+        let mut sink = LogSink::new(window, &stream_id, request.filter.clone())
+            .with_capture(LogCapture::open(&self.context(), &request.ns));
+        let mut ticker = interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                event = events.try_next() => {
+                    match event {
+                        Ok(Some(event)) => followed.on_event(&pods, &request.options, event, &line_tx),
+                        Ok(None) => break,
+                        Err(err) => {
+                            warn!("Pod watch for {} failed, retrying: {}", stream_id, err);
+                            sleep(Duration::from_secs(1)).await;
+                        },
+                    }
+                },
+                Some(event) = line_rx.recv() => {
+                    match event {
+                        LogEvent::Line(line) => sink.push(line),
+                        LogEvent::Ended { pod, container, error } => {
+                            followed.ended(&pod, &container);
+                            let reason = error.unwrap_or_else(|| "stream ended".to_string());
+                            sink.push(LogLine::marker(&pod, &container, "detached", format!("Stopped following {}/{}: {}", pod, container, reason)));
+                        }
+                    }
+                    if sink.is_full() {
+                        sink.flush();
+                    }
+                },
+                _ = ticker.tick() => {
+                    if let TailControl::Stop = sink.poll_control(rx) {
+                        break;
+                    }
+                    sink.flush();
+                }
+            }
+        }
+        followed.stop();
+        debug!("Finished tail for {}", stream_id);
+        Ok(())
+    }
+
+    pub fn tail_logs_for_pod(
+        &self,
+        window: Window,
+        pod: &str,
+        ns: &str,
+        options: &LogOptions,
+        filter: LogFilter,
+        rx: &Receiver<String>,
+    ) {
+        let result = self._tail_logs_for_pod(&window, pod, ns, options, filter, rx);
+        if let Err(err) = result {
+            error!("Failed to tail logs for {}: {}", pod, err);
+            send_error(&window, &format!("Failed to tail logs. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _tail_logs_for_pod(
+        &self,
+        window: &Window,
+        pod: &str,
+        ns: &str,
+        options: &LogOptions,
+        filter: LogFilter,
+        rx: &Receiver<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        info!("Fetching logs for {:?}", pod);
+        let client = match self.init_client().await {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        let pods: Api<Pod> = self.get_api(client, ns);
+        let (line_tx, mut line_rx) = unbounded_channel::<LogEvent>();
+        let container = options.container.clone().unwrap_or_default();
+        let handle = tokio::spawn(stream_container(pods, pod.to_string(), container, options.params(true), line_tx));
+        let mut sink = LogSink::new(window, pod, filter).with_capture(LogCapture::open(&self.context(), ns));
+        let mut ticker = interval(FLUSH_INTERVAL);
+        loop {
+            tokio::select! {
+                Some(event) = line_rx.recv() => {
+                    match event {
+                        LogEvent::Line(line) => sink.push(line),
+                        LogEvent::Ended { error, .. } => {
+                            // A clean end means the pod finished, which the frontend cannot tell apart
+                            // from a quiet container without the marker
+                            let reason = error.unwrap_or_else(|| "the container finished".to_string());
+                            sink.push(LogLine::marker(pod, "", "ended", format!("Log stream ended: {}", reason)));
+                            sink.flush();
+                            break;
+                        }
+                    }
+                    if sink.is_full() {
+                        sink.flush();
+                    }
+                },
+                _ = ticker.tick() => {
+                    if let TailControl::Stop = sink.poll_control(rx) {
+                        break;
+                    }
+                    sink.flush();
+                }
+            }
+        }
+        handle.abort();
+        debug!("Finished spawned task");
+        Ok(())
+    }
+
+    /// The label selector of a Deployment, StatefulSet, DaemonSet or Job.
+    pub(crate) async fn workload_selector(
+        &self,
+        client: kube::Client,
+        ns: &str,
+        kind: &str,
+        name: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let selector = match kind.to_lowercase().as_str() {
+            "deployment" | "deployments" => {
+                let api: Api<Deployment> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| label_selector_string(&s.selector))
+            },
+            "statefulset" | "statefulsets" => {
+                let api: Api<StatefulSet> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| label_selector_string(&s.selector))
+            },
+            "daemonset" | "daemonsets" => {
+                let api: Api<DaemonSet> = self.get_api(client, ns);
+                api.get(name).await?.spec.map(|s| label_selector_string(&s.selector))
+            },
+            "job" | "jobs" => {
+                let api: Api<Job> = self.get_api(client, ns);
+                api.get(name).await?.spec.and_then(|s| s.selector).map(|s| label_selector_string(&s))
+            },
+            _ => return Err(format!("Log tailing is not supported for {}", kind).into()),
+        };
+        selector
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| format!("{} {} has no selector", kind, name).into())
+    }
+}
+
+/// Pods and containers a tail over a pod watch has followed. Restarts are followed by the
+/// container streams themselves, so a container whose stream ended is not attached again.
+#[derive(Default)]
+pub(crate) struct Followed {
+    attached: HashMap<(String, String), JoinHandle<()>>,
+    finished: HashSet<(String, String)>,
+    /// Deleted pods, oldest first, so the next new pod can be reported as their replacement.
+    departed: VecDeque<String>,
+    /// Set once the first list of pods was handled. Containers found after that are new and
+    /// read from their start.
+    synced: bool,
+}
+
+impl Followed {
+    /// Attaches and detaches containers as the pod watch reports changes.
+    pub(crate) fn on_event(
+        &mut self,
+        pods: &Api<Pod>,
+        options: &LogOptions,
+        event: watcher::Event<Pod>,
+        tx: &UnboundedSender<LogEvent>,
+    ) {
+        match event {
+            watcher::Event::Applied(pod) => self.attach(pods, &pod, options, tx),
+            watcher::Event::Deleted(pod) => self.detach(&pod.name_any(), tx),
+            watcher::Event::Restarted(current) => {
+                let names: HashSet<String> = current.iter().map(|p| p.name_any()).collect();
+                let gone: HashSet<String> = self
+                    .attached
+                    .keys()
+                    .chain(self.finished.iter())
+                    .map(|(pod, _)| pod.clone())
+                    .filter(|pod| !names.contains(pod))
+                    .collect();
+                for pod in gone {
+                    self.detach(&pod, tx);
+                }
+                for pod in &current {
+                    self.attach(pods, pod, options, tx);
+                }
+                self.synced = true;
+            }
+        }
+    }
+
+    pub(crate) fn ended(&mut self, pod: &str, container: &str) {
+        let key = (pod.to_string(), container.to_string());
+        self.attached.remove(&key);
+        self.finished.insert(key);
+    }
+
+    pub(crate) fn stop(&mut self) {
+        for (_key, handle) in self.attached.drain() {
+            handle.abort();
+        }
+    }
+
+    fn knows(&self, pod: &str) -> bool {
+        self.attached.keys().chain(self.finished.iter()).any(|(p, _)| p == pod)
+    }
+
+    /// Starts a stream for each running or finished container of the pod that is not followed yet.
+    fn attach(&mut self, pods: &Api<Pod>, pod: &Pod, options: &LogOptions, tx: &UnboundedSender<LogEvent>) {
+        let name = pod.name_any();
+        let statuses = pod
+            .status
+            .as_ref()
+            .and_then(|s| s.container_statuses.clone())
+            .unwrap_or_default();
+        for status in statuses {
+            if options.container.as_ref().map(|c| *c != status.name).unwrap_or(false) {
+                continue;
+            }
+            let started = status
+                .state
+                .as_ref()
+                .map(|s| s.running.is_some() || s.terminated.is_some())
+                .unwrap_or(false);
+            let key = (name.clone(), status.name.clone());
+            if !started || self.attached.contains_key(&key) || self.finished.contains(&key) {
+                continue;
+            }
+            if self.synced && !self.knows(&name) {
+                if let Some(old) = self.departed.pop_front() {
+                    let _ = tx.send(LogEvent::Line(LogLine::marker(
+                        &name,
+                        "",
+                        "pod_replaced",
+                        format!("Pod {} was replaced by {}", old, name),
+                    )));
+                }
+            }
+            let mut params = options.params(true);
+            params.container = Some(status.name.clone());
+            if self.synced {
+                // A pod that shows up while tailing is new, so its log is read from the start
+                params.tail_lines = None;
+                params.since_seconds = None;
+            }
+            let _ = tx.send(LogEvent::Line(LogLine::marker(
+                &name,
+                &status.name,
+                "attached",
+                format!("Following {}/{}", name, status.name),
+            )));
+            let handle = tokio::spawn(stream_container(pods.clone(), name.clone(), status.name.clone(), params, tx.clone()));
+            self.attached.insert(key, handle);
+        }
+    }
+
+    fn detach(&mut self, pod: &str, tx: &UnboundedSender<LogEvent>) {
+        let keys: Vec<(String, String)> = self.attached.keys().filter(|(p, _)| p == pod).cloned().collect();
+        if self.knows(pod) {
+            self.departed.push_back(pod.to_string());
+        }
+        for key in keys {
+            if let Some(handle) = self.attached.remove(&key) {
+                handle.abort();
+                let _ = tx.send(LogEvent::Line(LogLine::marker(
+                    &key.0,
+                    &key.1,
+                    "detached",
+                    format!("Pod {} was deleted", key.0),
+                )));
+            }
+        }
+        self.finished.retain(|(p, _)| p != pod);
+    }
+}
+
+/// Where a followed container log got to. A new connection asks for the log since the last
+/// timestamp, and the lines it sends again are skipped.
+#[derive(Default)]
+struct LogPosition {
+    /// Lines are read with timestamps. They are removed again unless the tail asked for them.
+    keep_timestamps: bool,
+    last: Option<DateTime<FixedOffset>>,
+    /// Lines sent with the last timestamp.
+    at_last: usize,
+    /// Lines with the last timestamp still to skip after reconnecting.
+    replayed: usize,
+}
+
+impl LogPosition {
+    fn reconnecting(&mut self) {
+        self.replayed = self.at_last;
+    }
+
+    /// Returns the server timestamp in milliseconds and the line to send, or None for a line
+    /// that was sent before reconnecting.
+    fn accept(&mut self, line: &str) -> Option<(Option<i64>, String)> {
+        let (ts, message) = match line.split_once(' ') {
+            Some((ts, message)) => match DateTime::parse_from_rfc3339(ts) {
+                Ok(ts) => (ts, message),
+                Err(_) => return Some((None, line.to_string())),
+            },
+            None => return Some((None, line.to_string())),
+        };
+        match self.last {
+            Some(last) if ts < last => return None,
+            Some(last) if ts == last => {
+                if self.replayed > 0 {
+                    self.replayed -= 1;
+                    return None;
+                }
+                self.at_last += 1;
+            },
+            _ => {
+                self.last = Some(ts);
+                self.at_last = 1;
+                self.replayed = 0;
+            }
+        }
+        let line = if self.keep_timestamps { line.to_string() } else { message.to_string() };
+        Some((Some(ts.timestamp_millis()), line))
+    }
+
+    /// Rounded up, as the API server only takes whole seconds.
+    fn since_seconds(&self) -> Option<i64> {
+        self.last.map(|last| (Utc::now() - last.with_timezone(&Utc)).num_seconds().max(0) + 1)
+    }
+}
+
+/// What became of a container after its log stream stopped.
+enum ContainerCheck {
+    /// The pod was deleted or is being deleted.
+    Gone,
+    /// The pod has finished, so the container will not run again.
+    Finished,
+    Alive { restart_count: i32, running: bool },
+}
+
+/// Reads one container log, splitting the byte stream into lines. A followed log is reconnected
+/// when the connection drops and carried on to the next instance when the container restarts,
+/// until the pod finishes or goes away. Timestamps are always requested to resume without
+/// duplicates, and removed again unless they were asked for.
+pub(crate) async fn stream_container(
+    pods: Api<Pod>,
+    pod: String,
+    container: String,
+    mut params: LogParams,
+    tx: UnboundedSender<LogEvent>,
+) {
+    let container = if container.is_empty() {
+        match pods.get(&pod).await {
+            Ok(found) => pod_containers(&found).into_iter().find(|c| c.default).map(|c| c.name).unwrap_or_default(),
+            Err(_) => container,
+        }
+    } else {
+        container
+    };
+    if !container.is_empty() {
+        params.container = Some(container.clone());
+    }
+    let mut position = LogPosition {
+        keep_timestamps: params.timestamps,
+        ..LogPosition::default()
+    };
+    params.timestamps = true;
+    let mut restarts = match _check_container(&pods, &pod, &container).await {
+        Ok(ContainerCheck::Alive { restart_count, .. }) => Some(restart_count),
+        _ => None,
+    };
+    let mut announce: Option<LogLine> = None;
+    let mut delay = RECONNECT_DELAY;
+    let error = 'follow: loop {
+        let result = _read_log(&pods, &pod, &container, &params, &mut position, announce.take(), &tx).await;
+        if !params.follow {
+            break result.err().map(|e| e.to_string());
+        }
+        match &result {
+            Ok(lines) if *lines > 0 => delay = RECONNECT_DELAY,
+            Ok(_) => {},
+            Err(err) => debug!("Log stream of {}/{} stopped: {}", pod, container, err),
+        }
+        announce = Some(loop {
+            match _check_container(&pods, &pod, &container).await {
+                Ok(ContainerCheck::Gone) => break 'follow Some(format!("pod {} is gone", pod)),
+                Ok(ContainerCheck::Finished) => break 'follow result.err().map(|e| e.to_string()),
+                Ok(ContainerCheck::Alive { restart_count, running: true }) => {
+                    let restarted = restarts.map(|r| restart_count > r).unwrap_or(false);
+                    restarts = Some(restart_count);
+                    if restarted {
+                        break LogLine::marker(
+                            &pod,
+                            &container,
+                            "container_restarted",
+                            format!("Container {} of {} restarted", container, pod),
+                        );
+                    }
+                    sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    break LogLine::marker(&pod, &container, "reconnected", format!("Reconnected to {}/{}", pod, container));
+                },
+                Ok(ContainerCheck::Alive { running: false, .. }) => sleep(delay).await,
+                Err(err) => {
+                    debug!("Failed to check {}/{}: {}", pod, container, err);
+                    sleep(delay).await;
+                }
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        });
+        if let Some(since) = position.since_seconds() {
+            params.since_seconds = Some(since);
+            params.tail_lines = None;
+        }
+        position.reconnecting();
+    };
+    let _ = tx.send(LogEvent::Ended { pod, container, error });
+}
+
+/// Reads until the stream ends and returns how many lines were sent.
+async fn _read_log(
+    pods: &Api<Pod>,
+    pod: &str,
+    container: &str,
+    params: &LogParams,
+    position: &mut LogPosition,
+    announce: Option<LogLine>,
+    tx: &UnboundedSender<LogEvent>,
+) -> Result<usize, kube::Error> {
+    let mut chunks = pods.log_stream(pod, params).await?.boxed();
+    if let Some(marker) = announce {
+        let _ = tx.send(LogEvent::Line(marker));
+    }
+    let mut send = |line: &str| -> usize {
+        match position.accept(line) {
+            Some((ts, line)) => {
+                let _ = tx.send(LogEvent::Line(LogLine {
+                    pod: pod.to_string(),
+                    container: container.to_string(),
+                    line,
+                    ts,
+                    ..LogLine::default()
+                }));
+                1
+            },
+            None => 0,
+        }
+    };
+    let mut pending: Vec<u8> = Vec::new();
+    let mut sent = 0;
+    while let Some(chunk) = chunks.try_next().await? {
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let rest = pending.split_off(end + 1);
+            sent += send(String::from_utf8_lossy(&pending[..end]).trim_end_matches('\r'));
+            pending = rest;
+        }
+    }
+    if !pending.is_empty() {
+        sent += send(&String::from_utf8_lossy(&pending));
+    }
+    Ok(sent)
+}
+
+async fn _check_container(pods: &Api<Pod>, pod: &str, container: &str) -> Result<ContainerCheck, kube::Error> {
+    let pod = match pods.get_opt(pod).await? {
+        Some(pod) if pod.metadata.deletion_timestamp.is_none() => pod,
+        _ => return Ok(ContainerCheck::Gone),
+    };
+    let status = pod.status.unwrap_or_default();
+    if matches!(status.phase.as_deref(), Some("Succeeded") | Some("Failed")) {
+        return Ok(ContainerCheck::Finished);
+    }
+    let found = status
+        .container_statuses
+        .unwrap_or_default()
+        .into_iter()
+        .chain(status.init_container_statuses.unwrap_or_default())
+        .find(|s| s.name == container);
+    Ok(match found {
+        Some(found) => ContainerCheck::Alive {
+            restart_count: found.restart_count,
+            running: found.state.map(|s| s.running.is_some()).unwrap_or(false),
+        },
+        None => ContainerCheck::Alive { restart_count: 0, running: false },
+    })
+}
+
+/// Filters lines and hands them to the frontend in batches. The buffer is bounded, so a stream
+/// that produces more than the webview can take loses its oldest lines instead of growing.
+pub(crate) struct LogSink<'a> {
+    window: &'a Window,
+    stream_id: String,
+    filter: LogFilter,
+    parser: LogParser,
+    /// Stores every tailed line, filtered or not, when log capture is turned on.
+    capture: Option<LogCapture>,
+    buffer: VecDeque<LogLine>,
+    dropped: usize,
+    last_flush: Instant,
+}
+
+impl<'a> LogSink<'a> {
+    pub(crate) fn new(window: &'a Window, stream_id: &str, filter: LogFilter) -> Self {
+        LogSink {
+            window,
+            stream_id: stream_id.to_string(),
+            filter,
+            parser: LogParser::new(),
+            capture: None,
+            buffer: VecDeque::new(),
+            dropped: 0,
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub(crate) fn with_capture(mut self, capture: Option<LogCapture>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Parses a line and queues it if it passes the filter. Markers are always kept.
+    pub(crate) fn push(&mut self, mut line: LogLine) {
+        if line.marker.is_none() {
+            if let Some(capture) = self.capture.as_mut() {
+                capture.push(&line.pod, &line.container, &line.line, line.ts);
+            }
+            match self.filter.apply(&line.line) {
+                Some(matches) => line.matches = matches,
+                None => return,
+            }
+            line.parsed = self.parser.parse(&line.line);
+            if !self.filter.accepts_record(line.parsed.as_ref()) {
+                return;
+            }
+        }
+        if self.buffer.len() >= MAX_BUFFERED_LINES {
+            self.buffer.pop_front();
+            self.dropped += 1;
+        }
+        self.buffer.push_back(line);
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.buffer.len() >= MAX_BATCH_LINES && self.last_flush.elapsed() >= MIN_FLUSH_INTERVAL
+    }
+
+    pub(crate) fn poll_control(&mut self, rx: &Receiver<String>) -> TailControl {
+        self.filter.poll_control(rx, &self.stream_id)
+    }
+
+    /// Sends up to one batch. Lines dropped since the last batch are reported in front of it,
+    /// where the gap is.
+    pub(crate) fn flush(&mut self) {
+        if let Some(capture) = self.capture.as_mut() {
+            capture.flush();
+        }
+        if self.buffer.is_empty() && self.dropped == 0 {
+            return;
+        }
+        let count = self.buffer.len().min(MAX_BATCH_LINES);
+        let mut lines: Vec<LogLine> = self.buffer.drain(..count).collect();
+        if self.dropped > 0 {
+            let message = format!("{} lines dropped, the log view could not keep up", self.dropped);
+            lines.insert(0, LogLine::marker("", "", "dropped", message));
+        }
+        let batch = LogBatch {
+            stream: self.stream_id.clone(),
+            lines,
+            dropped: self.dropped,
+        };
+        self.dropped = 0;
+        self.last_flush = Instant::now();
+        let emitted = self.window.emit(
+            LOG_BATCH_CHANNEL,
+            Payload {
+                message: serde_json::to_string(&batch).unwrap(),
+                metadata: self.stream_id.clone(),
+            },
+        );
+        if let Err(err) = emitted {
+            warn!("Failed to send logs of {}: {}", self.stream_id, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepted(position: &mut LogPosition, lines: &[&str]) -> Vec<Option<String>> {
+        lines.iter().map(|line| position.accept(line).map(|(_, line)| line)).collect()
+    }
+
+    #[test]
+    fn strips_timestamps_unless_requested() {
+        let line = "2024-03-01T10:00:00.123456789Z listening on :8080";
+        let ts = Some(1709287200123);
+        let mut position = LogPosition::default();
+        assert_eq!(position.accept(line), Some((ts, "listening on :8080".to_string())));
+        let mut position = LogPosition { keep_timestamps: true, ..LogPosition::default() };
+        assert_eq!(position.accept(line), Some((ts, line.to_string())));
+    }
+
+    #[test]
+    fn passes_lines_without_a_timestamp() {
+        let mut position = LogPosition::default();
+        for line in ["no timestamp here", "single", ""].iter() {
+            assert_eq!(position.accept(line), Some((None, line.to_string())));
+        }
+        assert!(position.since_seconds().is_none());
+    }
+
+    #[test]
+    fn skips_older_lines() {
+        let mut position = LogPosition::default();
+        let lines = [
+            "2024-03-01T10:00:02Z b",
+            "2024-03-01T10:00:01Z a",
+            "2024-03-01T10:00:03Z c",
+            "2024-03-01T10:00:02Z b",
+        ];
+        let expected = [Some("b".to_string()), None, Some("c".to_string()), None];
+        assert_eq!(accepted(&mut position, &lines), expected);
+        assert!(position.since_seconds().unwrap() >= 1);
+    }
+
+    #[test]
+    fn skips_lines_replayed_with_the_last_timestamp_after_reconnecting() {
+        let mut position = LogPosition::default();
+        let sent = accepted(
+            &mut position,
+            &["2024-03-01T10:00:01Z a", "2024-03-01T10:00:02Z b", "2024-03-01T10:00:02Z c"],
+        );
+        assert!(sent.iter().all(|line| line.is_some()));
+
+        // The new connection starts a second early, so it repeats a, b and c before the new d
+        position.reconnecting();
+        let lines = [
+            "2024-03-01T10:00:01Z a",
+            "2024-03-01T10:00:02Z b",
+            "2024-03-01T10:00:02Z c",
+            "2024-03-01T10:00:02Z d",
+            "2024-03-01T10:00:03Z e",
+        ];
+        let expected = [None, None, None, Some("d".to_string()), Some("e".to_string())];
+        assert_eq!(accepted(&mut position, &lines), expected);
+
+        position.reconnecting();
+        let lines = ["2024-03-01T10:00:03Z e", "2024-03-01T10:00:03Z f"];
+        assert_eq!(accepted(&mut position, &lines), [None, Some("f".to_string())]);
+    }
+}
@@ -1,11 +1,30 @@
+pub(crate) mod alerts;
+pub(crate) mod audit;
+pub(crate) mod bulk;
+pub(crate) mod clone;
 pub(crate) mod common;
+pub(crate) mod cronjobs;
+pub(crate) mod dependents;
+pub(crate) mod export;
+pub(crate) mod images;
 pub(crate) mod kubeclient;
+pub(crate) mod labels;
+pub(crate) mod logexport;
+pub(crate) mod logfilter;
+pub(crate) mod logparse;
+pub(crate) mod logs;
+pub(crate) mod logtail;
 
 mod kubectl;
 mod metrics;
 pub(crate) mod models;
+pub(crate) mod nodes;
+pub(crate) mod rollout;
+mod schema;
+mod schedule;
 
 use crate::kube::common::{dispatch_to_frontend, init_client};
+use crate::kube::logs::LogOptions;
 use crate::kube::metrics::{get_all_pods, get_pod_metrics};
 use crate::kube::models::{CommandResult, Metric};
 use futures::{StreamExt, TryStreamExt};
@@ -218,8 +237,8 @@ async fn _stream_cpu_memory_for_pod(
     Ok(())
 }
 
-pub fn get_logs_for_pod(window: Window, cluster: &str, pod: &str, ns: &str) {
-    _get_logs_for_pod(window, cluster, pod, ns);
+pub fn get_logs_for_pod(window: Window, cluster: &str, pod: &str, ns: &str, options: &LogOptions) {
+    _get_logs_for_pod(window, cluster, pod, ns, options);
 }
 
 #[tokio::main]
@@ -228,19 +247,13 @@ async fn _get_logs_for_pod(
     cluster: &str,
     pod: &str,
     ns: &str,
+    options: &LogOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("Fetching logs for {:?}", pod);
     let client = init_client(cluster);
     let pods: Api<Pod> = Api::namespaced(client.await.unwrap(), ns);
     let mut logs = pods
-        .log_stream(
-            &pod,
-            &LogParams {
-                follow: false,
-                tail_lines: Some(100),
-                ..LogParams::default()
-            },
-        )
+        .log_stream(&pod, &options.params(false))
         .await?
         .boxed();
 
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::pin::Pin;
 use k8s_openapi::{ClusterResourceScope, NamespaceResourceScope};
-use kube::api::{ListParams, ObjectList, ObjectMeta};
+use kube::api::{DeleteParams, ListParams, ObjectList, ObjectMeta, PropagationPolicy};
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use tokio::io;
 use std::task::Context;
@@ -28,6 +28,7 @@ pub struct ResourceWithMetricsHolder {
     pub(crate) metrics: String,
     pub(crate) usage: Option<String>,
     pub (crate) metrics2: Option<String>,
+    pub(crate) paused: Option<Vec<String>>,
     pub(crate) ts: u128
 }
 
@@ -72,6 +73,50 @@ impl k8s_openapi::Metadata for NodeMetrics {
     }
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct DeleteOptions {
+    pub(crate) propagation: Option<PropagationPolicy>,
+    pub(crate) grace_period: Option<u32>,
+    pub(crate) force: bool,
+    pub(crate) timeout: u64,
+}
+
+impl DeleteOptions {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Result<Self, String> {
+        let propagation = match args.get("propagation").map(|p| p.to_lowercase()).as_deref() {
+            None | Some("") => None,
+            Some("foreground") => Some(PropagationPolicy::Foreground),
+            Some("background") => Some(PropagationPolicy::Background),
+            Some("orphan") => Some(PropagationPolicy::Orphan),
+            Some(other) => return Err(format!("Unknown propagation policy: {}", other)),
+        };
+        Ok(DeleteOptions {
+            propagation,
+            grace_period: args.get("grace_period").and_then(|g| g.parse().ok()),
+            force: args.get("force").map(|f| f == "true").unwrap_or(false),
+            timeout: args.get("timeout").and_then(|t| t.parse().ok()).unwrap_or(60),
+        })
+    }
+
+    /// Force deletion skips graceful termination, like `kubectl delete --force --grace-period=0`.
+    pub(crate) fn params(&self) -> DeleteParams {
+        DeleteParams {
+            propagation_policy: self.propagation.clone(),
+            grace_period_seconds: if self.force { Some(0) } else { self.grace_period },
+            ..DeleteParams::default()
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct DeleteStatus {
+    pub(crate) kind: String,
+    pub(crate) ns: String,
+    pub(crate) name: String,
+    pub(crate) status: String,
+    pub(crate) message: String,
+}
+
 #[derive(Clone, serde::Serialize, Default)]
 pub struct Metric {
     pub(crate) cpu: Option<String>,
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::error::Error;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::{Node, Pod};
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use kube::api::{Api, DeleteParams, EvictParams, ListParams, Patch, PatchParams, PostParams, ResourceExt};
+use kube::runtime::wait::{await_condition, conditions::is_deleted};
+use kube::Client;
+use serde_json::json;
+use tauri::Window;
+use tokio::time::{sleep, timeout_at, Duration, Instant};
+use crate::kube::audit::Mutation;
+use crate::kube::common::{dispatch_to_frontend, selector_matches};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::Payload;
+use crate::utils::send_error;
+
+const MIRROR_POD_ANNOTATION: &str = "kubernetes.io/config.mirror";
+const DRAIN_CONCURRENCY: usize = 5;
+const DEFAULT_DRAIN_TIMEOUT_SECONDS: u64 = 300;
+const MAX_EVICTION_BACKOFF_SECONDS: u64 = 30;
+
+pub const DRAIN_PROGRESS_CHANNEL: &str = "app::drain_progress";
+
+#[derive(Clone, Debug, Default)]
+pub struct DrainOptions {
+    pub(crate) delete_emptydir_data: bool,
+    pub(crate) force: bool,
+    pub(crate) grace_period: Option<u32>,
+    pub(crate) timeout: u64,
+}
+
+impl DrainOptions {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Self {
+        DrainOptions {
+            delete_emptydir_data: args.get("delete_emptydir_data").map(|v| v == "true").unwrap_or(false),
+            force: args.get("force").map(|v| v == "true").unwrap_or(false),
+            grace_period: args.get("grace_period").and_then(|v| v.parse().ok()),
+            timeout: args
+                .get("timeout")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_DRAIN_TIMEOUT_SECONDS),
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct DrainEvent {
+    pub(crate) node: String,
+    pub(crate) pod: String,
+    pub(crate) status: String,
+    pub(crate) message: String,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct DrainResult {
+    pub(crate) node: String,
+    pub(crate) evicted: Vec<String>,
+    pub(crate) skipped: Vec<String>,
+    pub(crate) failed: Vec<String>,
+    pub(crate) completed: bool,
+}
+
+enum PodDisposition {
+    Evict,
+    Skip(String),
+    Block(String),
+}
+
+impl KubeClientManager {
+    pub fn set_node_unschedulable(&self, window: &Window, node: &str, unschedulable: bool, cmd: &str) {
+        let result = self._set_node_unschedulable(window, node, unschedulable, cmd);
+        if let Err(err) = result {
+            let action = if unschedulable { "cordon" } else { "uncordon" };
+            error!("Failed to {} node {}: {}", action, node, err);
+            send_error(window, &format!("Failed to {} node. Reason: {}", action, err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _set_node_unschedulable(
+        &self,
+        window: &Window,
+        node: &str,
+        unschedulable: bool,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                self.cordon(client, node, unschedulable).await?;
+                dispatch_to_frontend(window, cmd, "success".to_string());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to update node. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn drain_node(&self, window: &Window, node: &str, options: DrainOptions, cmd: &str) {
+        let result = self._drain_node(window, node, &options, cmd);
+        if let Err(err) = result {
+            error!("Failed to drain node {}: {}", node, err);
+            send_error(window, &format!("Failed to drain node. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _drain_node(
+        &self,
+        window: &Window,
+        node: &str,
+        options: &DrainOptions,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                self.cordon(client.clone(), node, true).await?;
+                let pods_api: Api<Pod> = Api::all(client.clone());
+                let lp = ListParams::default().fields(&format!("spec.nodeName={}", node));
+                let mut result = DrainResult {
+                    node: node.to_string(),
+                    ..DrainResult::default()
+                };
+                let mut to_evict: Vec<Pod> = Vec::new();
+                for pod in pods_api.list(&lp).await? {
+                    let key = _pod_key(&pod);
+                    match _drain_disposition(&pod, options) {
+                        PodDisposition::Evict => to_evict.push(pod),
+                        PodDisposition::Skip(reason) => {
+                            _emit_drain_event(window, node, &key, "skipped", &reason);
+                            result.skipped.push(key);
+                        },
+                        PodDisposition::Block(reason) => {
+                            _emit_drain_event(window, node, &key, "failed", &reason);
+                            result.failed.push(key);
+                        }
+                    }
+                }
+                if !result.failed.is_empty() {
+                    dispatch_to_frontend(window, cmd, serde_json::to_string(&result).unwrap());
+                    return Err(format!(
+                        "{} pods on {} cannot be evicted with the selected options",
+                        result.failed.len(),
+                        node
+                    )
+                    .into());
+                }
+
+                let deadline = Instant::now() + Duration::from_secs(options.timeout);
+                let outcomes: Vec<(String, bool)> = futures::stream::iter(to_evict)
+                    .map(|pod| self.evict_pod(window, client.clone(), node, pod, options, deadline))
+                    .buffer_unordered(DRAIN_CONCURRENCY)
+                    .collect()
+                    .await;
+                for (key, evicted) in outcomes {
+                    if evicted {
+                        result.evicted.push(key);
+                    } else {
+                        result.failed.push(key);
+                    }
+                }
+                result.completed = result.failed.is_empty();
+                let outcome = if result.completed {
+                    Ok(())
+                } else {
+                    Err(format!("Failed to evict {}", result.failed.join(", ")))
+                };
+                self.audit(Mutation::typed::<Node>("drain", "", node), &outcome);
+                info!("Drain of {} finished: {} evicted, {} failed", node, result.evicted.len(), result.failed.len());
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&result).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to drain node. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    async fn cordon(&self, client: Client, node: &str, unschedulable: bool) -> Result<(), Box<dyn Error>> {
+        let api: Api<Node> = Api::all(client);
+        let patch = Patch::Merge(json!({ "spec": { "unschedulable": unschedulable } }));
+        let result = api.patch(node, &PatchParams::default(), &patch).await;
+        let verb = if unschedulable { "cordon" } else { "uncordon" };
+        self.audit(Mutation::typed::<Node>(verb, "", node), &result);
+        result?;
+        info!("Set unschedulable={} on node {}", unschedulable, node);
+        Ok(())
+    }
+
+    /// Evicts a pod, backing off while a PodDisruptionBudget refuses the eviction, and then
+    /// waits for the pod to be deleted. Returns the pod key and whether it is gone.
+    async fn evict_pod(
+        &self,
+        window: &Window,
+        client: Client,
+        node: &str,
+        pod: Pod,
+        options: &DrainOptions,
+        deadline: Instant,
+    ) -> (String, bool) {
+        let key = _pod_key(&pod);
+        let name = pod.name_any();
+        let api: Api<Pod> = Api::namespaced(client.clone(), &pod.namespace().unwrap_or_default());
+        let params = EvictParams {
+            delete_options: Some(DeleteParams {
+                grace_period_seconds: options.grace_period,
+                ..DeleteParams::default()
+            }),
+            post_options: PostParams::default(),
+        };
+        let mut backoff = Duration::from_secs(2);
+        _emit_drain_event(window, node, &key, "evicting", "Evicting pod");
+        loop {
+            match api.evict(&name, &params).await {
+                Ok(_) => break,
+                Err(kube::Error::Api(ae)) if ae.code == 404 => {
+                    _emit_drain_event(window, node, &key, "deleted", "Pod already deleted");
+                    return (key, true);
+                },
+                Err(kube::Error::Api(ae)) if ae.code == 429 => {
+                    if Instant::now() + backoff > deadline {
+                        _emit_drain_event(window, node, &key, "failed", "Timed out waiting for PodDisruptionBudget to allow eviction");
+                        return (key, false);
+                    }
+                    let pdbs = self.matching_pdbs(client.clone(), &pod).await;
+                    let blocker = if pdbs.is_empty() {
+                        "a PodDisruptionBudget".to_string()
+                    } else {
+                        format!("PodDisruptionBudget {}", pdbs.join(", "))
+                    };
+                    let message = format!("Eviction blocked by {}. Retrying in {}s", blocker, backoff.as_secs());
+                    _emit_drain_event(window, node, &key, "waiting_pdb", &message);
+                    sleep(backoff).await;
+                    backoff = std::cmp::min(backoff * 2, Duration::from_secs(MAX_EVICTION_BACKOFF_SECONDS));
+                },
+                Err(err) => {
+                    _emit_drain_event(window, node, &key, "failed", &err.to_string());
+                    return (key, false);
+                }
+            }
+        }
+
+        _emit_drain_event(window, node, &key, "evicted", "Waiting for pod to terminate");
+        let uid = pod.uid().unwrap_or_default();
+        match timeout_at(deadline, await_condition(api, &name, is_deleted(&uid))).await {
+            Ok(Ok(_)) => {
+                _emit_drain_event(window, node, &key, "deleted", "Pod deleted");
+                (key, true)
+            },
+            Ok(Err(err)) => {
+                _emit_drain_event(window, node, &key, "failed", &err.to_string());
+                (key, false)
+            },
+            Err(_) => {
+                _emit_drain_event(window, node, &key, "failed", "Timed out waiting for pod to terminate");
+                (key, false)
+            }
+        }
+    }
+
+    async fn matching_pdbs(&self, client: Client, pod: &Pod) -> Vec<String> {
+        let api: Api<PodDisruptionBudget> = Api::namespaced(client, &pod.namespace().unwrap_or_default());
+        match api.list(&ListParams::default()).await {
+            Ok(pdbs) => pdbs
+                .iter()
+                .filter(|pdb| {
+                    pdb.spec
+                        .as_ref()
+                        .and_then(|s| s.selector.as_ref())
+                        .map(|selector| selector_matches(selector, pod.labels()))
+                        .unwrap_or(false)
+                })
+                .map(|pdb| pdb.name_any())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+fn _drain_disposition(pod: &Pod, options: &DrainOptions) -> PodDisposition {
+    if pod.annotations().contains_key(MIRROR_POD_ANNOTATION) {
+        return PodDisposition::Skip("Mirror pod is managed by the kubelet".to_string());
+    }
+    let controller = pod.owner_references().iter().find(|o| o.controller == Some(true));
+    if let Some(owner) = controller {
+        if owner.kind == "DaemonSet" {
+            return PodDisposition::Skip(format!("Managed by DaemonSet {}", owner.name));
+        }
+    }
+    let phase = pod.status.as_ref().and_then(|s| s.phase.clone()).unwrap_or_default();
+    let finished = phase == "Succeeded" || phase == "Failed";
+    if controller.is_none() && !finished && !options.force {
+        return PodDisposition::Block("Pod is not managed by a controller. Use force to delete it".to_string());
+    }
+    let uses_empty_dir = pod
+        .spec
+        .as_ref()
+        .and_then(|s| s.volumes.as_ref())
+        .map(|volumes| volumes.iter().any(|v| v.empty_dir.is_some()))
+        .unwrap_or(false);
+    if uses_empty_dir && !finished && !options.delete_emptydir_data {
+        return PodDisposition::Block("Pod uses emptyDir storage. Allow deleting emptyDir data to evict it".to_string());
+    }
+    PodDisposition::Evict
+}
+
+fn _pod_key(pod: &Pod) -> String {
+    format!("{}/{}", pod.namespace().unwrap_or_default(), pod.name_any())
+}
+
+fn _emit_drain_event(window: &Window, node: &str, pod: &str, status: &str, message: &str) {
+    let event = DrainEvent {
+        node: node.to_string(),
+        pod: pod.to_string(),
+        status: status.to_string(),
+        message: message.to_string(),
+    };
+    window
+        .emit(
+            DRAIN_PROGRESS_CHANNEL,
+            Payload {
+                message: serde_json::to_string(&event).unwrap(),
+                metadata: node.to_string(),
+            },
+        )
+        .unwrap();
+}
@@ -0,0 +1,852 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::apps::v1::{ControllerRevision, DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use kube::api::{Api, DynamicObject, ListParams, Patch, PatchParams, PostParams, ResourceExt};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Client, Resource};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tauri::Window;
+use tokio::time::{sleep, timeout_at, Duration, Instant};
+use crate::kube::audit::Mutation;
+use crate::kube::common::{dispatch_to_frontend, label_selector_string};
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::Payload;
+use crate::utils::send_error;
+
+const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+const CHANGE_CAUSE_ANNOTATION: &str = "kubernetes.io/change-cause";
+const HASH_LABELS: [&str; 2] = ["pod-template-hash", "controller-revision-hash"];
+const DEFAULT_PROGRESS_DEADLINE_SECONDS: u64 = 600;
+
+pub const ROLLOUT_STATUS_CHANNEL: &str = "app::rollout_status";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum WorkloadKind {
+    Deployment,
+    StatefulSet,
+    DaemonSet,
+}
+
+impl WorkloadKind {
+    pub(crate) fn from_kind(kind: &str) -> Option<WorkloadKind> {
+        match kind.to_lowercase().trim() {
+            "deployment" | "deployments" => Some(WorkloadKind::Deployment),
+            "statefulset" | "statefulsets" => Some(WorkloadKind::StatefulSet),
+            "daemonset" | "daemonsets" => Some(WorkloadKind::DaemonSet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct TemplateChange {
+    pub(crate) path: String,
+    pub(crate) current: Option<Value>,
+    pub(crate) revision: Option<Value>,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct RolloutRevision {
+    pub(crate) revision: i64,
+    pub(crate) source: String,
+    pub(crate) change_cause: Option<String>,
+    pub(crate) images: Vec<String>,
+    pub(crate) created: Option<String>,
+    pub(crate) current: bool,
+    pub(crate) changes: Vec<TemplateChange>,
+}
+
+#[derive(Clone, serde::Serialize, Default, Debug)]
+pub struct RolloutStatus {
+    pub(crate) kind: String,
+    pub(crate) namespace: String,
+    pub(crate) name: String,
+    pub(crate) phase: String,
+    pub(crate) message: String,
+    pub(crate) generation: i64,
+    pub(crate) observed_generation: i64,
+    pub(crate) replicas: i32,
+    pub(crate) updated_replicas: i32,
+    pub(crate) ready_replicas: i32,
+    pub(crate) available_replicas: i32,
+    pub(crate) done: bool,
+}
+
+impl RolloutStatus {
+    fn progressing(&mut self, message: String) -> Self {
+        self.phase = "progressing".to_string();
+        self.message = message;
+        self.done = false;
+        self.clone()
+    }
+
+    fn finished(&mut self, phase: &str, message: String) -> Self {
+        self.phase = phase.to_string();
+        self.message = message;
+        self.done = true;
+        self.clone()
+    }
+}
+
+/// A revision as read from its ReplicaSet or ControllerRevision, before it is compared
+/// against the live pod template.
+struct RevisionSource {
+    revision: i64,
+    name: String,
+    annotations: BTreeMap<String, String>,
+    created: Option<String>,
+    template: Value,
+    data: Option<Value>,
+}
+
+struct WorkloadHistory {
+    template: Value,
+    current_revision: i64,
+    revisions: Vec<RevisionSource>,
+}
+
+impl KubeClientManager {
+    pub fn get_rollout_history(&self, window: &Window, ns: &str, kind: &str, name: &str, cmd: &str) {
+        let result = self._get_rollout_history(window, ns, kind, name, cmd);
+        if let Err(err) = result {
+            error!("Failed to fetch rollout history for {}: {}", name, err);
+            send_error(window, &format!("Failed to fetch rollout history. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _get_rollout_history(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let workload = _workload_kind(kind)?;
+                let history = self.workload_history(client, ns, workload, name).await?;
+                let current_template = normalize_template(history.template.clone());
+                let mut revisions: Vec<RolloutRevision> = Vec::new();
+                for source in history.revisions {
+                    let template = normalize_template(source.template);
+                    let mut changes = Vec::new();
+                    diff_values("", &current_template, &template, &mut changes);
+                    revisions.push(RolloutRevision {
+                        revision: source.revision,
+                        source: source.name,
+                        change_cause: source.annotations.get(CHANGE_CAUSE_ANNOTATION).cloned(),
+                        images: template_images(&template),
+                        created: source.created,
+                        current: source.revision == history.current_revision,
+                        changes,
+                    });
+                }
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&revisions).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to fetch rollout history. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn rollback_rollout(&self, window: &Window, ns: &str, kind: &str, name: &str, revision: i64, cmd: &str) {
+        let result = self._rollback_rollout(window, ns, kind, name, revision, cmd);
+        if let Err(err) = result {
+            error!("Failed to roll back {}: {}", name, err);
+            send_error(window, &format!("Failed to roll back. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _rollback_rollout(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        revision: i64,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let workload = _workload_kind(kind)?;
+                let history = self.workload_history(client.clone(), ns, workload, name).await?;
+                let target = _find_rollback_target(&history, revision)?;
+                match workload {
+                    WorkloadKind::Deployment => {
+                        let api: Api<Deployment> = self.get_api(client, ns);
+                        let mut deployment = api.get(name).await?;
+                        if let Some(spec) = &deployment.spec {
+                            if spec.paused.unwrap_or(false) {
+                                return Err(format!("Deployment {} is paused. Resume it before rolling back", name).into());
+                            }
+                        }
+                        let mut template = target.template.clone();
+                        remove_hash_labels(&mut template);
+                        if let Some(spec) = deployment.spec.as_mut() {
+                            spec.template = serde_json::from_value(template)?;
+                        }
+                        if let Some(cause) = target.annotations.get(CHANGE_CAUSE_ANNOTATION) {
+                            deployment
+                                .annotations_mut()
+                                .insert(CHANGE_CAUSE_ANNOTATION.to_string(), cause.to_string());
+                        }
+                        let result = api.replace(name, &PostParams::default(), &deployment).await;
+                        self.audit(Mutation::typed::<Deployment>("rollback", ns, name), &result);
+                        result?;
+                    },
+                    WorkloadKind::StatefulSet => {
+                        let api: Api<StatefulSet> = self.get_api(client, ns);
+                        let data = target.data.clone().ok_or("Controller revision has no data")?;
+                        let result = api.patch(name, &PatchParams::default(), &Patch::Strategic(data)).await;
+                        self.audit(Mutation::typed::<StatefulSet>("rollback", ns, name), &result);
+                        result?;
+                    },
+                    WorkloadKind::DaemonSet => {
+                        let api: Api<DaemonSet> = self.get_api(client, ns);
+                        let data = target.data.clone().ok_or("Controller revision has no data")?;
+                        let result = api.patch(name, &PatchParams::default(), &Patch::Strategic(data)).await;
+                        self.audit(Mutation::typed::<DaemonSet>("rollback", ns, name), &result);
+                        result?;
+                    }
+                }
+                info!("Rolled back {} {} to revision {}", kind, name, target.revision);
+                dispatch_to_frontend(window, cmd, target.revision.to_string());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to roll back. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn rollout_status(&self, window: &Window, ns: &str, kind: &str, name: &str, timeout: Option<u64>, cmd: &str) {
+        let result = self._rollout_status(window, ns, kind, name, timeout, cmd);
+        if let Err(err) = result {
+            error!("Failed to track rollout of {}: {}", name, err);
+            send_error(window, &format!("Failed to track rollout. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _rollout_status(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        timeout: Option<u64>,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let workload = _workload_kind(kind)?;
+                let status = self.track_rollout(window, client, ns, workload, name, timeout).await?;
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&status).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to track rollout. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_rollout_paused(&self, window: &Window, ns: &str, name: &str, paused: bool, cmd: &str) {
+        let result = self._set_rollout_paused(window, ns, name, paused, cmd);
+        if let Err(err) = result {
+            let action = if paused { "pause" } else { "resume" };
+            error!("Failed to {} rollout of {}: {}", action, name, err);
+            send_error(window, &format!("Failed to {} rollout. Reason: {}", action, err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _set_rollout_paused(
+        &self,
+        window: &Window,
+        ns: &str,
+        name: &str,
+        paused: bool,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let api: Api<Deployment> = self.get_api(client.clone(), ns);
+                let patch = Patch::Merge(json!({ "spec": { "paused": paused } }));
+                let result = api.patch(name, &PatchParams::default(), &patch).await;
+                let verb = if paused { "pause" } else { "resume" };
+                self.audit(Mutation::typed::<Deployment>(verb, ns, name), &result);
+                result?;
+                info!("Set paused={} on deployment {}", paused, name);
+                dispatch_to_frontend(window, cmd, "success".to_string());
+                if !paused {
+                    let tracked = self.track_rollout(window, client, ns, WorkloadKind::Deployment, name, None).await;
+                    if let Err(err) = tracked {
+                        warn!("Failed to track rollout of {}: {}", name, err);
+                    }
+                }
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to update rollout. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn scale_workload(&self, window: &Window, ns: &str, kind: &str, name: &str, replicas: i32, cmd: &str) {
+        let result = self._scale_workload(window, ns, kind, name, replicas, cmd);
+        if let Err(err) = result {
+            error!("Failed to scale {}: {}", name, err);
+            send_error(window, &format!("Failed to scale. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _scale_workload(
+        &self,
+        window: &Window,
+        ns: &str,
+        kind: &str,
+        name: &str,
+        replicas: i32,
+        cmd: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let workload = _workload_kind(kind)?;
+                self.scale(client.clone(), ns, workload, name, replicas).await?;
+                dispatch_to_frontend(window, cmd, "success".to_string());
+                let tracked = self.track_rollout(window, client, ns, workload, name, None).await;
+                if let Err(err) = tracked {
+                    warn!("Failed to track rollout of {}: {}", name, err);
+                }
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to scale. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) async fn scale(
+        &self,
+        client: Client,
+        ns: &str,
+        workload: WorkloadKind,
+        name: &str,
+        replicas: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let patch = Patch::Merge(json!({ "spec": { "replicas": replicas } }));
+        let params = PatchParams::default();
+        match workload {
+            WorkloadKind::Deployment => {
+                let api: Api<Deployment> = self.get_api(client, ns);
+                let before = api.get_scale(name).await?;
+                let result = api.patch_scale(name, &params, &patch).await;
+                self.audit(Mutation::typed::<Deployment>("scale", ns, name).before(&before), &result);
+                result?;
+            },
+            WorkloadKind::StatefulSet => {
+                let api: Api<StatefulSet> = self.get_api(client, ns);
+                let before = api.get_scale(name).await?;
+                let result = api.patch_scale(name, &params, &patch).await;
+                self.audit(Mutation::typed::<StatefulSet>("scale", ns, name).before(&before), &result);
+                result?;
+            },
+            WorkloadKind::DaemonSet => {
+                return Err("DaemonSets cannot be scaled".into());
+            }
+        }
+        info!("Scaled {:?} {} to {} replicas", workload, name, replicas);
+        Ok(())
+    }
+
+    /// Starts rollout tracking for an object that was just applied, if it is a workload.
+    pub(crate) async fn track_applied_object(&self, window: &Window, client: Client, obj: &DynamicObject, ns: &str) {
+        let kind = obj.types.as_ref().map(|t| t.kind.clone()).unwrap_or_default();
+        if let Some(workload) = WorkloadKind::from_kind(&kind) {
+            let namespace = obj.namespace().unwrap_or_else(|| ns.to_string());
+            let result = self.track_rollout(window, client, &namespace, workload, &obj.name_any(), None).await;
+            if let Err(err) = result {
+                warn!("Failed to track rollout of {}: {}", obj.name_any(), err);
+            }
+        }
+    }
+
+    /// Follows a rollout until it completes, fails or passes its deadline, emitting each
+    /// change of progress on the rollout status channel. The final status is returned.
+    pub(crate) async fn track_rollout(
+        &self,
+        window: &Window,
+        client: Client,
+        ns: &str,
+        workload: WorkloadKind,
+        name: &str,
+        timeout: Option<u64>,
+    ) -> Result<RolloutStatus, Box<dyn Error>> {
+        match workload {
+            WorkloadKind::Deployment => {
+                let api: Api<Deployment> = self.get_api(client, ns);
+                let deadline = api
+                    .get(name)
+                    .await?
+                    .spec
+                    .and_then(|s| s.progress_deadline_seconds)
+                    .map(|s| s as u64)
+                    .unwrap_or(DEFAULT_PROGRESS_DEADLINE_SECONDS);
+                follow_rollout(window, api, name, timeout.unwrap_or(deadline + 30), _deployment_status).await
+            },
+            WorkloadKind::StatefulSet => {
+                let api: Api<StatefulSet> = self.get_api(client, ns);
+                follow_rollout(window, api, name, timeout.unwrap_or(DEFAULT_PROGRESS_DEADLINE_SECONDS), _stateful_set_status).await
+            },
+            WorkloadKind::DaemonSet => {
+                let api: Api<DaemonSet> = self.get_api(client, ns);
+                follow_rollout(window, api, name, timeout.unwrap_or(DEFAULT_PROGRESS_DEADLINE_SECONDS), _daemon_set_status).await
+            }
+        }
+    }
+
+    async fn workload_history(
+        &self,
+        client: Client,
+        ns: &str,
+        workload: WorkloadKind,
+        name: &str,
+    ) -> Result<WorkloadHistory, Box<dyn Error>> {
+        match workload {
+            WorkloadKind::Deployment => {
+                let api: Api<Deployment> = self.get_api(client.clone(), ns);
+                let deployment = api.get(name).await?;
+                let uid = deployment.uid().unwrap_or_default();
+                let spec = deployment.spec.clone().unwrap_or_default();
+                let rs_api: Api<ReplicaSet> = self.get_api(client, ns);
+                let lp = ListParams::default().labels(&label_selector_string(&spec.selector));
+                let mut revisions = Vec::new();
+                for rs in rs_api.list(&lp).await? {
+                    if !rs.owner_references().iter().any(|owner| owner.uid == uid) {
+                        continue;
+                    }
+                    let revision = _revision_from_annotations(rs.annotations());
+                    let template = serde_json::to_value(rs.spec.as_ref().and_then(|s| s.template.as_ref()))?;
+                    revisions.push(RevisionSource {
+                        revision,
+                        name: rs.name_any(),
+                        annotations: rs.annotations().clone(),
+                        created: rs.creation_timestamp().map(|ts| ts.0.to_rfc3339()),
+                        template,
+                        data: None,
+                    });
+                }
+                revisions.sort_by(|a, b| b.revision.cmp(&a.revision));
+                Ok(WorkloadHistory {
+                    template: serde_json::to_value(&spec.template)?,
+                    current_revision: _revision_from_annotations(deployment.annotations()),
+                    revisions,
+                })
+            },
+            WorkloadKind::StatefulSet => {
+                let api: Api<StatefulSet> = self.get_api(client.clone(), ns);
+                let sts = api.get(name).await?;
+                let uid = sts.uid().unwrap_or_default();
+                let spec = sts.spec.clone().unwrap_or_default();
+                let update_revision = sts.status.as_ref().and_then(|s| s.update_revision.clone());
+                let revisions = self
+                    .controller_revisions(client, ns, &label_selector_string(&spec.selector), &uid)
+                    .await?;
+                let current_revision = revisions
+                    .iter()
+                    .find(|r| Some(&r.name) == update_revision.as_ref())
+                    .or_else(|| revisions.first())
+                    .map(|r| r.revision)
+                    .unwrap_or(0);
+                Ok(WorkloadHistory {
+                    template: serde_json::to_value(&spec.template)?,
+                    current_revision,
+                    revisions,
+                })
+            },
+            WorkloadKind::DaemonSet => {
+                let api: Api<DaemonSet> = self.get_api(client.clone(), ns);
+                let ds = api.get(name).await?;
+                let uid = ds.uid().unwrap_or_default();
+                let spec = ds.spec.clone().unwrap_or_default();
+                let revisions = self
+                    .controller_revisions(client, ns, &label_selector_string(&spec.selector), &uid)
+                    .await?;
+                let current_revision = revisions.first().map(|r| r.revision).unwrap_or(0);
+                Ok(WorkloadHistory {
+                    template: serde_json::to_value(&spec.template)?,
+                    current_revision,
+                    revisions,
+                })
+            }
+        }
+    }
+
+    async fn controller_revisions(
+        &self,
+        client: Client,
+        ns: &str,
+        selector: &str,
+        owner_uid: &str,
+    ) -> Result<Vec<RevisionSource>, Box<dyn Error>> {
+        let api: Api<ControllerRevision> = self.get_api(client, ns);
+        let lp = ListParams::default().labels(selector);
+        let mut revisions = Vec::new();
+        for cr in api.list(&lp).await? {
+            if !cr.owner_references().iter().any(|owner| owner.uid == owner_uid) {
+                continue;
+            }
+            let data = cr.data.as_ref().map(|raw| raw.0.clone());
+            let template = data
+                .as_ref()
+                .and_then(|d| d.pointer("/spec/template"))
+                .cloned()
+                .unwrap_or(Value::Null);
+            revisions.push(RevisionSource {
+                revision: cr.revision,
+                name: cr.name_any(),
+                annotations: cr.annotations().clone(),
+                created: cr.creation_timestamp().map(|ts| ts.0.to_rfc3339()),
+                template,
+                data,
+            });
+        }
+        revisions.sort_by(|a, b| b.revision.cmp(&a.revision));
+        Ok(revisions)
+    }
+}
+
+async fn follow_rollout<K, F>(
+    window: &Window,
+    api: Api<K>,
+    name: &str,
+    timeout: u64,
+    evaluate: F,
+) -> Result<RolloutStatus, Box<dyn Error>>
+where
+    K: Resource + Clone + DeserializeOwned + Debug + Send + 'static,
+    F: Fn(&K) -> RolloutStatus,
+{
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let lp = ListParams::default().fields(&format!("metadata.name={}", name));
+    let mut stream = watcher(api, lp).applied_objects().boxed();
+    let mut last: Option<RolloutStatus> = None;
+    loop {
+        match timeout_at(deadline, stream.try_next()).await {
+            Ok(Ok(Some(obj))) => {
+                let status = evaluate(&obj);
+                let changed = match &last {
+                    Some(prev) => prev.message != status.message || prev.phase != status.phase,
+                    None => true,
+                };
+                if changed {
+                    _emit_rollout_status(window, &status);
+                }
+                if status.done {
+                    return Ok(status);
+                }
+                last = Some(status);
+            },
+            Ok(Ok(None)) => {
+                return Err(format!("Watch for {} ended before the rollout finished", name).into());
+            },
+            Ok(Err(err)) => {
+                warn!("Rollout watch for {} failed, retrying: {}", name, err);
+                sleep(Duration::from_millis(2000)).await;
+            },
+            Err(_) => {
+                let mut status = last.unwrap_or_default();
+                let status = status.finished(
+                    "timeout",
+                    format!("Rollout of {} did not finish within {} seconds", name, timeout),
+                );
+                _emit_rollout_status(window, &status);
+                return Ok(status);
+            }
+        }
+    }
+}
+
+fn _emit_rollout_status(window: &Window, status: &RolloutStatus) {
+    window
+        .emit(
+            ROLLOUT_STATUS_CHANNEL,
+            Payload {
+                message: serde_json::to_string(status).unwrap(),
+                metadata: status.name.clone(),
+            },
+        )
+        .unwrap();
+}
+
+fn _base_status<K: Resource>(kind: &str, obj: &K) -> RolloutStatus {
+    let meta = obj.meta();
+    RolloutStatus {
+        kind: kind.to_string(),
+        namespace: meta.namespace.clone().unwrap_or_default(),
+        name: meta.name.clone().unwrap_or_default(),
+        generation: meta.generation.unwrap_or(0),
+        ..RolloutStatus::default()
+    }
+}
+
+fn _deployment_status(deployment: &Deployment) -> RolloutStatus {
+    let mut result = _base_status("Deployment", deployment);
+    let name = result.name.clone();
+    let spec_replicas = deployment.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let status = deployment.status.clone().unwrap_or_default();
+    result.observed_generation = status.observed_generation.unwrap_or(0);
+    result.replicas = status.replicas.unwrap_or(0);
+    result.updated_replicas = status.updated_replicas.unwrap_or(0);
+    result.ready_replicas = status.ready_replicas.unwrap_or(0);
+    result.available_replicas = status.available_replicas.unwrap_or(0);
+
+    if result.observed_generation < result.generation {
+        return result.progressing("Waiting for deployment spec update to be observed".to_string());
+    }
+    // A paused rollout makes no progress, so report it instead of waiting for the deadline.
+    if deployment.spec.as_ref().and_then(|s| s.paused).unwrap_or(false) {
+        return result.finished("paused", format!("Deployment {} is paused", name));
+    }
+    let deadline_exceeded = status
+        .conditions
+        .unwrap_or_default()
+        .iter()
+        .any(|c| c.type_ == "Progressing" && c.reason.as_deref() == Some("ProgressDeadlineExceeded"));
+    if deadline_exceeded {
+        return result.finished("failed", format!("Deployment {} exceeded its progress deadline", name));
+    }
+    if result.updated_replicas < spec_replicas {
+        return result.progressing(format!(
+            "Waiting for rollout to finish: {} out of {} new replicas have been updated",
+            result.updated_replicas, spec_replicas
+        ));
+    }
+    if result.replicas > result.updated_replicas {
+        return result.progressing(format!(
+            "Waiting for rollout to finish: {} old replicas are pending termination",
+            result.replicas - result.updated_replicas
+        ));
+    }
+    if result.available_replicas < result.updated_replicas {
+        return result.progressing(format!(
+            "Waiting for rollout to finish: {} of {} updated replicas are available",
+            result.available_replicas, result.updated_replicas
+        ));
+    }
+    result.finished("complete", format!("Deployment {} successfully rolled out", name))
+}
+
+fn _stateful_set_status(sts: &StatefulSet) -> RolloutStatus {
+    let mut result = _base_status("StatefulSet", sts);
+    let name = result.name.clone();
+    let spec = sts.spec.clone().unwrap_or_default();
+    let spec_replicas = spec.replicas.unwrap_or(1);
+    let status = sts.status.clone().unwrap_or_default();
+    result.observed_generation = status.observed_generation.unwrap_or(0);
+    result.replicas = status.replicas;
+    result.updated_replicas = status.updated_replicas.unwrap_or(0);
+    result.ready_replicas = status.ready_replicas.unwrap_or(0);
+    result.available_replicas = status.available_replicas.unwrap_or(0);
+
+    if result.observed_generation < result.generation {
+        return result.progressing("Waiting for statefulset spec update to be observed".to_string());
+    }
+    let strategy = spec.update_strategy.unwrap_or_default();
+    if strategy.type_.as_deref() == Some("OnDelete") {
+        return result.finished("complete", format!("StatefulSet {} uses the OnDelete strategy and is not tracked", name));
+    }
+    if result.ready_replicas < spec_replicas {
+        return result.progressing(format!(
+            "Waiting for {} pods to be ready",
+            spec_replicas - result.ready_replicas
+        ));
+    }
+    if let Some(partition) = strategy.rolling_update.and_then(|r| r.partition) {
+        if result.updated_replicas < spec_replicas - partition {
+            return result.progressing(format!(
+                "Waiting for partitioned roll out to finish: {} out of {} new pods have been updated",
+                result.updated_replicas,
+                spec_replicas - partition
+            ));
+        }
+        return result.finished("complete", format!("Partitioned roll out of {} complete", name));
+    }
+    if status.update_revision != status.current_revision {
+        return result.progressing(format!(
+            "Waiting for rolling update to complete: {} pods at revision {}",
+            result.updated_replicas,
+            status.update_revision.unwrap_or_default()
+        ));
+    }
+    result.finished("complete", format!("StatefulSet {} successfully rolled out", name))
+}
+
+fn _daemon_set_status(ds: &DaemonSet) -> RolloutStatus {
+    let mut result = _base_status("DaemonSet", ds);
+    let name = result.name.clone();
+    let strategy = ds.spec.as_ref().and_then(|s| s.update_strategy.clone()).unwrap_or_default();
+    let status = ds.status.clone().unwrap_or_default();
+    let desired = status.desired_number_scheduled;
+    result.observed_generation = status.observed_generation.unwrap_or(0);
+    result.replicas = desired;
+    result.updated_replicas = status.updated_number_scheduled.unwrap_or(0);
+    result.ready_replicas = status.number_ready;
+    result.available_replicas = status.number_available.unwrap_or(0);
+
+    if result.observed_generation < result.generation {
+        return result.progressing("Waiting for daemon set spec update to be observed".to_string());
+    }
+    if strategy.type_.as_deref() == Some("OnDelete") {
+        return result.finished("complete", format!("DaemonSet {} uses the OnDelete strategy and is not tracked", name));
+    }
+    if result.updated_replicas < desired {
+        return result.progressing(format!(
+            "Waiting for daemon set rollout to finish: {} out of {} new pods have been updated",
+            result.updated_replicas, desired
+        ));
+    }
+    if result.available_replicas < desired {
+        return result.progressing(format!(
+            "Waiting for daemon set rollout to finish: {} of {} updated pods are available",
+            result.available_replicas, desired
+        ));
+    }
+    result.finished("complete", format!("DaemonSet {} successfully rolled out", name))
+}
+
+fn _workload_kind(kind: &str) -> Result<WorkloadKind, Box<dyn Error>> {
+    WorkloadKind::from_kind(kind)
+        .ok_or_else(|| format!("Rollouts are not supported for {}", kind).into())
+}
+
+fn _revision_from_annotations(annotations: &BTreeMap<String, String>) -> i64 {
+    annotations
+        .get(REVISION_ANNOTATION)
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Revision 0 follows `kubectl rollout undo` and means the revision before the current one.
+fn _find_rollback_target(history: &WorkloadHistory, revision: i64) -> Result<&RevisionSource, Box<dyn Error>> {
+    let target = if revision == 0 {
+        history
+            .revisions
+            .iter()
+            .find(|r| r.revision < history.current_revision)
+    } else {
+        history.revisions.iter().find(|r| r.revision == revision)
+    };
+    match target {
+        Some(target) if target.revision == history.current_revision => {
+            Err(format!("Revision {} is already the current revision", revision).into())
+        },
+        Some(target) => Ok(target),
+        None => Err(format!("Revision {} not found", revision).into()),
+    }
+}
+
+fn remove_hash_labels(template: &mut Value) {
+    if let Some(labels) = template
+        .pointer_mut("/metadata/labels")
+        .and_then(|l| l.as_object_mut())
+    {
+        for label in HASH_LABELS {
+            labels.remove(label);
+        }
+    }
+}
+
+fn normalize_template(mut template: Value) -> Value {
+    remove_hash_labels(&mut template);
+    if let Some(obj) = template.as_object_mut() {
+        obj.remove("$patch");
+    }
+    template
+}
+
+pub(crate) fn template_images(template: &Value) -> Vec<String> {
+    let mut images = Vec::new();
+    for key in ["initContainers", "containers"] {
+        if let Some(containers) = template.pointer(&format!("/spec/{}", key)).and_then(|c| c.as_array()) {
+            for container in containers {
+                if let Some(image) = container.get("image").and_then(|i| i.as_str()) {
+                    images.push(image.to_string());
+                }
+            }
+        }
+    }
+    images
+}
+
+fn diff_values(path: &str, current: &Value, revision: &Value, changes: &mut Vec<TemplateChange>) {
+    match (current, revision) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                diff_values(
+                    &child,
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        },
+        (Value::Array(a), Value::Array(b)) if _named_items(a) && _named_items(b) => {
+            let names: Vec<&str> = a.iter().chain(b.iter()).filter_map(|v| v["name"].as_str()).collect();
+            let mut seen: Vec<&str> = Vec::new();
+            for name in names {
+                if seen.contains(&name) {
+                    continue;
+                }
+                seen.push(name);
+                let find = |items: &Vec<Value>| items.iter().find(|v| v["name"].as_str() == Some(name)).cloned();
+                diff_values(
+                    &format!("{}[{}]", path, name),
+                    &find(a).unwrap_or(Value::Null),
+                    &find(b).unwrap_or(Value::Null),
+                    changes,
+                );
+            }
+        },
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (idx, (x, y)) in a.iter().zip(b.iter()).enumerate() {
+                diff_values(&format!("{}[{}]", path, idx), x, y, changes);
+            }
+        },
+        _ => {
+            if current != revision {
+                changes.push(TemplateChange {
+                    path: path.to_string(),
+                    current: if current.is_null() { None } else { Some(current.clone()) },
+                    revision: if revision.is_null() { None } else { Some(revision.clone()) },
+                });
+            }
+        }
+    }
+}
+
+fn _named_items(items: &[Value]) -> bool {
+    !items.is_empty() && items.iter().all(|v| v.get("name").and_then(|n| n.as_str()).is_some())
+}
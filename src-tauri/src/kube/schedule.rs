@@ -0,0 +1,283 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// How far ahead to look for the next fire time before giving up, e.g. for `0 0 30 2 *`.
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A standard five field cron expression as accepted by the CronJob controller, including the
+/// `@hourly` style macros and an optional `CRON_TZ=` or `TZ=` prefix.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// Cron matches either day field when both are restricted, and both otherwise.
+    day_or: bool,
+    timezone: Option<Tz>,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Schedule, String> {
+        let mut expression = expression.trim();
+        let mut timezone = None;
+        if expression.starts_with("CRON_TZ=") || expression.starts_with("TZ=") {
+            let (tz, rest) = expression.split_once(char::is_whitespace).ok_or("Missing schedule after timezone")?;
+            let tz = tz.split_once('=').map(|(_, tz)| tz).unwrap_or_default();
+            timezone = Some(parse_timezone(tz)?);
+            expression = rest.trim();
+        }
+        let expanded = match expression {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Expected 5 fields in schedule '{}', found {}", expression, fields.len()));
+        }
+        let mut days_of_week = parse_field(fields[4], 0, 7, &DAY_NAMES)?;
+        // 7 is an alias for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+        Ok(Schedule {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days_of_month: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTH_NAMES)?,
+            days_of_week,
+            day_or: !_is_wildcard(fields[2]) && !_is_wildcard(fields[4]),
+            timezone,
+        })
+    }
+
+    /// The timezone from the expression prefix wins over the one given on the CronJob spec.
+    pub fn with_default_timezone(mut self, timezone: Option<&str>) -> Result<Schedule, String> {
+        if self.timezone.is_none() {
+            if let Some(tz) = timezone {
+                self.timezone = Some(parse_timezone(tz)?);
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(Tz::UTC)
+    }
+
+    /// The first fire time strictly after `after`. Wall clock times skipped by a daylight saving
+    /// transition do not fire, and repeated ones fire on their first occurrence.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let tz = self.timezone();
+        let start = after.with_timezone(&tz).naive_local() + Duration::minutes(1);
+        let mut candidate = start.date().and_hms(start.hour(), start.minute(), 0);
+        let limit = candidate + Duration::days(MAX_SEARCH_DAYS);
+        while candidate < limit {
+            if !self.months[candidate.month() as usize] {
+                candidate = _first_of_next_month(candidate.date())?.and_hms(0, 0, 0);
+                continue;
+            }
+            if !self.day_matches(candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms(0, 0, 0);
+                continue;
+            }
+            if !self.hours[candidate.hour() as usize] {
+                candidate = candidate.date().and_hms(candidate.hour(), 0, 0) + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes[candidate.minute() as usize] {
+                candidate += Duration::minutes(1);
+                continue;
+            }
+            match _resolve_local(&tz, &candidate) {
+                Some(time) if time > after => return Some(time),
+                _ => candidate += Duration::minutes(1),
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = self.days_of_month[date.day() as usize];
+        let dow = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        if self.day_or {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+}
+
+fn parse_timezone(tz: &str) -> Result<Tz, String> {
+    tz.parse::<Tz>().map_err(|_| format!("Unknown timezone '{}'", tz))
+}
+
+/// Parses one field into a lookup table indexed by value, so index 0 is unused for fields
+/// starting at 1.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("Invalid step in '{}'", part))?;
+                if step == 0 {
+                    return Err(format!("Step must be positive in '{}'", part));
+                }
+                (range, step)
+            },
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (_parse_value(start, min, names)?, _parse_value(end, min, names)?)
+        } else {
+            let value = _parse_value(range, min, names)?;
+            // `5/15` means every 15 starting at 5
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("Value out of range {}-{} in '{}'", min, max, part));
+        }
+        let mut value = start;
+        while value <= end {
+            allowed[value as usize] = true;
+            value += step;
+        }
+    }
+    Ok(allowed)
+}
+
+fn _parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32, String> {
+    let lower = value.to_lowercase();
+    if let Some(index) = names.iter().position(|n| *n == lower) {
+        return Ok(index as u32 + min);
+    }
+    value.parse().map_err(|_| format!("Invalid value '{}'", value))
+}
+
+fn _is_wildcard(field: &str) -> bool {
+    field == "*" || field == "?"
+}
+
+fn _first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    if date.month() == 12 {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+    }
+}
+
+fn _resolve_local(tz: &Tz, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(local).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(field: &str, min: u32, max: u32, names: &[&str]) -> Vec<u32> {
+        let allowed = parse_field(field, min, max, names).unwrap();
+        (min..=max).filter(|v| allowed[*v as usize]).collect()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<String> {
+        let after = DateTime::parse_from_rfc3339(after).unwrap().with_timezone(&Utc);
+        Schedule::parse(expression)
+            .unwrap()
+            .next_after(after)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn parses_ranges_steps_and_lists() {
+        let cases: [(&str, &[u32]); 6] = [
+            ("1-5", &[1, 2, 3, 4, 5]),
+            ("*/15", &[0, 15, 30, 45]),
+            ("10-30/10", &[10, 20, 30]),
+            ("5/20", &[5, 25, 45]),
+            ("1,3,50-52", &[1, 3, 50, 51, 52]),
+            ("59", &[59]),
+        ];
+        for (field, expected) in cases.iter() {
+            assert_eq!(values(field, 0, 59, &[]), *expected, "{}", field);
+        }
+    }
+
+    #[test]
+    fn parses_named_days_and_months() {
+        assert_eq!(values("jan-mar", 1, 12, &MONTH_NAMES), vec![1, 2, 3]);
+        assert_eq!(values("JUN,Dec", 1, 12, &MONTH_NAMES), vec![6, 12]);
+        assert_eq!(values("mon-fri", 0, 7, &DAY_NAMES), vec![1, 2, 3, 4, 5]);
+        assert_eq!(values("sun,sat", 0, 7, &DAY_NAMES), vec![0, 6]);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for (field, min, max) in [("60", 0, 59), ("*/0", 0, 59), ("5-1", 0, 59), ("abc", 0, 59), ("0", 1, 31)].iter() {
+            assert!(parse_field(field, *min, *max, &[]).is_err(), "{}", field);
+        }
+        for expression in ["* * * *", "@every 5m", "TZ=Nowhere/City * * * * *", "CRON_TZ=UTC"].iter() {
+            assert!(Schedule::parse(expression).is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn expands_macros() {
+        let after = "2024-01-15T10:20:00Z";
+        let cases = [
+            ("@hourly", "2024-01-15 11:00"),
+            ("@daily", "2024-01-16 00:00"),
+            ("@midnight", "2024-01-16 00:00"),
+            ("@weekly", "2024-01-21 00:00"),
+            ("@monthly", "2024-02-01 00:00"),
+            ("@yearly", "2025-01-01 00:00"),
+            ("@annually", "2025-01-01 00:00"),
+        ];
+        for (expression, expected) in cases.iter() {
+            assert_eq!(next(expression, after).as_deref(), Some(*expected), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn finds_the_next_fire_time() {
+        let cases = [
+            // Strictly after, so a time that matches exactly moves on to the next day
+            ("20 10 * * *", "2024-01-15T10:20:00Z", Some("2024-01-16 10:20")),
+            ("*/15 9-17 * * mon-fri", "2024-01-19T17:50:00Z", Some("2024-01-22 09:00")),
+            // 7 is Sunday as well as 0
+            ("0 12 * * 7", "2024-01-15T10:20:00Z", Some("2024-01-21 12:00")),
+            // Both day fields restricted, so either one matching is enough
+            ("0 0 13 * fri", "2024-01-01T00:00:00Z", Some("2024-01-05 00:00")),
+            ("0 0 13 * *", "2024-01-01T00:00:00Z", Some("2024-01-13 00:00")),
+            ("30 23 31 12 *", "2024-12-31T23:30:00Z", Some("2025-12-31 23:30")),
+            ("0 0 31 * *", "2024-01-31T00:00:00Z", Some("2024-03-31 00:00")),
+            ("0 0 1 6 *", "2024-01-15T10:20:00Z", Some("2024-06-01 00:00")),
+            ("0 0 * jan *", "2024-12-15T10:20:00Z", Some("2025-01-01 00:00")),
+            ("0 0 29 2 *", "2024-03-01T00:00:00Z", Some("2028-02-29 00:00")),
+            ("0 0 30 2 *", "2024-01-01T00:00:00Z", None),
+        ];
+        for (expression, after, expected) in cases.iter() {
+            assert_eq!(next(expression, after).as_deref(), *expected, "{} after {}", expression, after);
+        }
+    }
+
+    #[test]
+    fn applies_the_timezone() {
+        // Tokyo has no daylight saving, so 09:00 there is always 00:00 UTC
+        assert_eq!(next("CRON_TZ=Asia/Tokyo 0 9 * * *", "2024-01-14T23:00:00Z").as_deref(), Some("2024-01-15 00:00"));
+        let schedule = Schedule::parse("TZ=Asia/Tokyo 0 9 * * *").unwrap().with_default_timezone(Some("UTC")).unwrap();
+        assert_eq!(schedule.timezone(), Tz::Asia__Tokyo);
+        let schedule = Schedule::parse("0 9 * * *").unwrap().with_default_timezone(Some("Asia/Tokyo")).unwrap();
+        assert_eq!(schedule.timezone(), Tz::Asia__Tokyo);
+    }
+}
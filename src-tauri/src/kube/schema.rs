@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use kube::Client;
+use serde_json::{Map, Value};
+use tauri::Window;
+use crate::kube::common::dispatch_to_frontend;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::kube::Payload;
+use crate::utils::send_error;
+
+pub const VALIDATION_CHANNEL: &str = "app::validation_errors";
+
+/// The v2 document has no content hash, so it is refetched after this long.
+const V2_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_REF_DEPTH: usize = 64;
+const QUANTITY_SCHEMA: &str = "io.k8s.apimachinery.pkg.api.resource.Quantity";
+
+#[derive(Clone, serde::Serialize, Default, Debug)]
+pub struct ValidationError {
+    pub(crate) document: usize,
+    pub(crate) line: usize,
+    pub(crate) path: String,
+    pub(crate) message: String,
+}
+
+#[derive(Clone, serde::Serialize, Default)]
+pub struct ValidationReport {
+    pub(crate) valid: bool,
+    /// False when the cluster schema could not be loaded and nothing was checked.
+    pub(crate) checked: bool,
+    pub(crate) errors: Vec<ValidationError>,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A schema document together with the prefix its `$ref`s use.
+struct SchemaDocument {
+    root: Value,
+    ref_prefix: &'static str,
+}
+
+impl SchemaDocument {
+    fn definitions(&self) -> Option<&Map<String, Value>> {
+        if self.ref_prefix.starts_with("#/components") {
+            self.root["components"]["schemas"].as_object()
+        } else {
+            self.root["definitions"].as_object()
+        }
+    }
+
+    fn find_kind(&self, group: &str, version: &str, kind: &str) -> Option<&Value> {
+        self.definitions()?.values().find(|schema| {
+            schema["x-kubernetes-group-version-kind"]
+                .as_array()
+                .map(|gvks| {
+                    gvks.iter().any(|gvk| {
+                        gvk["group"].as_str() == Some(group)
+                            && gvk["version"].as_str() == Some(version)
+                            && gvk["kind"].as_str() == Some(kind)
+                    })
+                })
+                .unwrap_or(false)
+        })
+    }
+
+    /// Follows `$ref`, including the `allOf: [{$ref}]` wrapping used by v3 documents.
+    /// Returns the schema and the name of the last definition it came from.
+    fn resolve<'a>(&'a self, mut schema: &'a Value) -> (&'a Value, Option<&'a str>) {
+        let mut name = None;
+        for _ in 0..MAX_REF_DEPTH {
+            let reference = schema["$ref"]
+                .as_str()
+                .or_else(|| schema["allOf"][0]["$ref"].as_str());
+            match reference.and_then(|r| r.strip_prefix(self.ref_prefix)) {
+                Some(definition) => match self.definitions().and_then(|d| d.get(definition)) {
+                    Some(target) => {
+                        name = Some(definition);
+                        schema = target;
+                    },
+                    None => break,
+                },
+                None => break,
+            }
+        }
+        (schema, name)
+    }
+}
+
+impl KubeClientManager {
+    pub fn validate_resource(&self, window: &Window, resource_str: &str, cmd: &str) {
+        let result = self._validate_resource(window, resource_str, cmd);
+        if let Err(err) = result {
+            error!("Failed to validate resource: {}", err);
+            send_error(window, &format!("Failed to validate resource. Reason: {}", err));
+        }
+    }
+
+    #[tokio::main]
+    async fn _validate_resource(&self, window: &Window, resource_str: &str, cmd: &str) -> Result<(), Box<dyn Error>> {
+        let client = self.init_client().await;
+        match client {
+            Some(client) => {
+                let report = self.validate_manifests(client, resource_str).await;
+                dispatch_to_frontend(window, cmd, serde_json::to_string(&report).unwrap());
+                Ok(())
+            },
+            None => {
+                send_error(window, "Failed to validate resource. Reason Kubeclient failed.");
+                Ok(())
+            }
+        }
+    }
+
+    /// Validates every document in the text against the cluster schema. Documents whose kind is
+    /// not in the schema, for example custom resources without a structural schema, are skipped.
+    pub(crate) async fn validate_manifests(&self, client: Client, text: &str) -> ValidationReport {
+        let mut report = ValidationReport { valid: true, checked: true, errors: Vec::new() };
+        let mut v2: Option<SchemaDocument> = None;
+        let mut v3_index: Option<Option<Value>> = None;
+        let mut v3_documents: HashMap<String, Option<SchemaDocument>> = HashMap::new();
+
+        for (index, (start_line, document)) in split_documents(text).into_iter().enumerate() {
+            let value: Value = match serde_yaml::from_str::<Value>(&document) {
+                Ok(Value::Null) => continue,
+                Ok(value) => value,
+                Err(err) => {
+                    let line = err.location().map(|l| l.line()).unwrap_or(1);
+                    report.errors.push(ValidationError {
+                        document: index,
+                        line: start_line + line - 1,
+                        path: String::new(),
+                        message: format!("Invalid YAML: {}", err),
+                    });
+                    continue;
+                }
+            };
+            let api_version = value["apiVersion"].as_str().unwrap_or_default().to_string();
+            let kind = value["kind"].as_str().unwrap_or_default().to_string();
+            if api_version.is_empty() || kind.is_empty() {
+                report.errors.push(ValidationError {
+                    document: index,
+                    line: start_line,
+                    path: String::new(),
+                    message: "apiVersion and kind are required".to_string(),
+                });
+                continue;
+            }
+            let (group, version) = match api_version.split_once('/') {
+                Some((group, version)) => (group.to_string(), version.to_string()),
+                None => (String::new(), api_version.clone()),
+            };
+
+            if v3_index.is_none() {
+                v3_index = Some(self.openapi_v3_index(client.clone()).await);
+            }
+            let mut schema_doc: Option<&SchemaDocument> = None;
+            if let Some(Some(v3)) = &v3_index {
+                let path = if group.is_empty() {
+                    format!("api/{}", version)
+                } else {
+                    format!("apis/{}/{}", group, version)
+                };
+                if !v3_documents.contains_key(&path) {
+                    let url = v3["paths"][&path]["serverRelativeURL"].as_str().map(|u| u.to_string());
+                    let loaded = match url {
+                        Some(url) => self.cached_schema(client.clone(), &url).await.map(|root| SchemaDocument {
+                            root,
+                            ref_prefix: "#/components/schemas/",
+                        }),
+                        None => None,
+                    };
+                    v3_documents.insert(path.clone(), loaded);
+                }
+                schema_doc = v3_documents.get(&path).and_then(|d| d.as_ref());
+            }
+            if schema_doc.is_none() {
+                if v2.is_none() {
+                    v2 = self
+                        .cached_schema(client.clone(), "/openapi/v2")
+                        .await
+                        .map(|root| SchemaDocument { root, ref_prefix: "#/definitions/" });
+                }
+                schema_doc = v2.as_ref();
+            }
+            let schema_doc = match schema_doc {
+                Some(schema_doc) => schema_doc,
+                None => {
+                    warn!("No OpenAPI schema available, skipping validation");
+                    report.checked = false;
+                    report.valid = report.errors.is_empty();
+                    return report;
+                }
+            };
+            let schema = match schema_doc.find_kind(&group, &version, &kind) {
+                Some(schema) => schema,
+                None => continue,
+            };
+            let mut errors: Vec<(Vec<Segment>, String)> = Vec::new();
+            validate_value(schema_doc, &value, schema, &mut Vec::new(), &mut errors);
+            let lines: Vec<&str> = document.lines().collect();
+            for (path, message) in errors {
+                report.errors.push(ValidationError {
+                    document: index,
+                    line: start_line + locate(&lines, &path),
+                    path: _path_string(&path),
+                    message,
+                });
+            }
+        }
+        report.valid = report.errors.is_empty();
+        report
+    }
+
+    /// Validates manifests before they are sent to the cluster. Errors are reported to the
+    /// frontend and stop the apply. When no schema can be loaded the apply goes ahead.
+    pub(crate) async fn check_manifests(&self, window: &Window, client: Client, text: &str) -> bool {
+        let report = self.validate_manifests(client, text).await;
+        if report.valid {
+            return true;
+        }
+        emit_validation_errors(window, &report);
+        let first = &report.errors[0];
+        send_error(
+            window,
+            &format!(
+                "Validation failed with {} error(s). Line {}: {}",
+                report.errors.len(),
+                first.line,
+                first.message
+            ),
+        );
+        false
+    }
+
+    async fn openapi_v3_index(&self, client: Client) -> Option<Value> {
+        match _get_json(client, "/openapi/v3").await {
+            Ok(index) => Some(index),
+            Err(err) => {
+                debug!("OpenAPI v3 is not available, falling back to v2: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Loads a schema document from the disk cache for the current context, fetching it when
+    /// missing. v3 urls carry a content hash, so a changed schema lands in a new cache file.
+    async fn cached_schema(&self, client: Client, url: &str) -> Option<Value> {
+        let file = _cache_file(&self.context(), url);
+        let fresh = fs::metadata(&file)
+            .and_then(|m| m.modified())
+            .map(|modified| url.contains("hash=") || modified.elapsed().unwrap_or_default() < V2_CACHE_TTL)
+            .unwrap_or(false);
+        if fresh {
+            if let Some(schema) = fs::read_to_string(&file).ok().and_then(|s| serde_json::from_str(&s).ok()) {
+                return Some(schema);
+            }
+        }
+        match _get_json(client, url).await {
+            Ok(schema) => {
+                if let Some(dir) = file.parent() {
+                    let _ = fs::create_dir_all(dir);
+                }
+                if let Err(err) = fs::write(&file, schema.to_string()) {
+                    warn!("Failed to cache schema {}: {}", url, err);
+                }
+                Some(schema)
+            },
+            Err(err) => {
+                warn!("Failed to fetch schema {}: {}", url, err);
+                None
+            }
+        }
+    }
+}
+
+async fn _get_json(client: Client, url: &str) -> Result<Value, Box<dyn Error>> {
+    let request = http::Request::get(url).body(Vec::new())?;
+    let text = client.request_text(request).await?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn _cache_file(context: &str, url: &str) -> PathBuf {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect()
+    };
+    let mut path: PathBuf = dirs::home_dir().unwrap();
+    path.push(".nirops");
+    path.push("schemas");
+    path.push(sanitize(context));
+    path.push(format!("{}.json", sanitize(url.trim_start_matches('/'))));
+    path
+}
+
+fn validate_value(
+    doc: &SchemaDocument,
+    value: &Value,
+    schema: &Value,
+    path: &mut Vec<Segment>,
+    errors: &mut Vec<(Vec<Segment>, String)>,
+) {
+    let (schema, definition) = doc.resolve(schema);
+    if value.is_null() {
+        return;
+    }
+    if schema["x-kubernetes-int-or-string"].as_bool() == Some(true) || definition == Some(QUANTITY_SCHEMA) {
+        if !(value.is_string() || value.is_i64() || value.is_u64() || (definition.is_some() && value.is_number())) {
+            errors.push((path.clone(), "Expected an integer or a string".to_string()));
+        }
+        return;
+    }
+    let expected = schema["type"].as_str().unwrap_or_default();
+    let matches = match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        _ => true,
+    };
+    if !matches {
+        errors.push((path.clone(), format!("Expected {} but found {}", _article(expected), _type_name(value))));
+        return;
+    }
+
+    if let Some(items) = value.as_array() {
+        if schema["items"].is_object() {
+            for (i, item) in items.iter().enumerate() {
+                path.push(Segment::Index(i));
+                validate_value(doc, item, &schema["items"], path, errors);
+                path.pop();
+            }
+        }
+        return;
+    }
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return,
+    };
+    if let Some(required) = schema["required"].as_array() {
+        for field in required.iter().filter_map(|f| f.as_str()) {
+            if !object.contains_key(field) {
+                errors.push((path.clone(), format!("Missing required field \"{}\"", field)));
+            }
+        }
+    }
+    if schema["x-kubernetes-preserve-unknown-fields"].as_bool() == Some(true) {
+        return;
+    }
+    let properties = schema["properties"].as_object();
+    let additional = &schema["additionalProperties"];
+    if properties.is_none() && !additional.is_object() {
+        return;
+    }
+    for (key, child) in object {
+        path.push(Segment::Key(key.clone()));
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => validate_value(doc, child, child_schema, path, errors),
+            None if additional.is_object() => validate_value(doc, child, additional, path, errors),
+            None => errors.push((path.clone(), format!("Unknown field \"{}\"", key))),
+        }
+        path.pop();
+    }
+}
+
+/// Splits multi-document YAML, returning each document with the 1-based line it starts on.
+fn split_documents(text: &str) -> Vec<(usize, String)> {
+    let mut documents = Vec::new();
+    let mut start = 1;
+    let mut current: Vec<&str> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_end() == "---" {
+            documents.push((start, current.join("\n")));
+            current.clear();
+            start = i + 2;
+        } else {
+            current.push(line);
+        }
+    }
+    documents.push((start, current.join("\n")));
+    documents
+}
+
+struct LineInfo<'a> {
+    /// Column of the `-` when the line starts a sequence item.
+    dash: Option<usize>,
+    /// Column where the mapping content of the line starts.
+    indent: usize,
+    content: &'a str,
+}
+
+fn _line_info(line: &str) -> Option<LineInfo<'_>> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+    let column = line.len() - trimmed.len();
+    if trimmed == "-" || trimmed.starts_with("- ") {
+        let rest = trimmed[1..].trim_start();
+        let indent = line.len() - rest.len();
+        Some(LineInfo { dash: Some(column), indent, content: rest })
+    } else {
+        Some(LineInfo { dash: None, indent: column, content: trimmed })
+    }
+}
+
+fn _is_key(content: &str, key: &str) -> bool {
+    [key.to_string(), format!("\"{}\"", key), format!("'{}'", key)]
+        .iter()
+        .any(|k| content.starts_with(k.as_str()) && content[k.len()..].trim_start().starts_with(':'))
+}
+
+/// Finds the 0-based line of a path in a block style YAML document by following indentation.
+/// Falls back to the deepest ancestor that could be found.
+fn locate(lines: &[&str], path: &[Segment]) -> usize {
+    let infos: Vec<Option<LineInfo>> = lines.iter().map(|l| _line_info(l)).collect();
+    let mut found = 0;
+    let (mut start, mut end) = (0, lines.len());
+    // The first line of a sequence item also holds the first key of the item
+    let mut include_start = true;
+    for segment in path {
+        let first = if include_start { start } else { start + 1 };
+        let range: Vec<(usize, &LineInfo)> = (first..end)
+            .filter_map(|i| infos[i].as_ref().map(|info| (i, info)))
+            .collect();
+        let hit = match segment {
+            Segment::Key(key) => {
+                let indent = range.iter().map(|(_, info)| info.indent).min();
+                range
+                    .iter()
+                    .find(|(_, info)| Some(info.indent) == indent && _is_key(info.content, key))
+                    .map(|(i, info)| (*i, info.indent, false))
+            },
+            Segment::Index(index) => {
+                let dash = range.iter().filter_map(|(_, info)| info.dash).min();
+                range
+                    .iter()
+                    .filter(|(_, info)| info.dash.is_some() && info.dash == dash)
+                    .nth(*index)
+                    .map(|(i, info)| (*i, info.dash.unwrap_or_default(), true))
+            },
+        };
+        let (line, column, item) = match hit {
+            Some(hit) => hit,
+            None => break,
+        };
+        found = line;
+        start = line;
+        include_start = item;
+        end = (line + 1..end)
+            .find(|i| match &infos[*i] {
+                Some(info) => info.indent <= column || (item && info.dash == Some(column)),
+                None => false,
+            })
+            .unwrap_or(end);
+    }
+    found
+}
+
+fn _path_string(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(key);
+            },
+            Segment::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+fn _type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_f64() => "a number",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn _article(expected: &str) -> String {
+    match expected {
+        "object" => "an object".to_string(),
+        "array" => "a list".to_string(),
+        "integer" => "an integer".to_string(),
+        other => format!("a {}", other),
+    }
+}
+
+/// Tells the frontend about validation errors that stopped an apply or edit.
+pub(crate) fn emit_validation_errors(window: &Window, report: &ValidationReport) {
+    window
+        .emit(
+            VALIDATION_CHANNEL,
+            Payload {
+                message: serde_json::to_string(report).unwrap(),
+                metadata: String::new(),
+            },
+        )
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document(definitions: Value) -> SchemaDocument {
+        SchemaDocument { root: json!({ "definitions": definitions }), ref_prefix: "#/definitions/" }
+    }
+
+    fn errors(doc: &SchemaDocument, value: Value, schema: &Value) -> Vec<(String, String)> {
+        let mut errors = Vec::new();
+        validate_value(doc, &value, schema, &mut Vec::new(), &mut errors);
+        errors.into_iter().map(|(path, message)| (_path_string(&path), message)).collect()
+    }
+
+    #[test]
+    fn splits_documents_with_start_lines() {
+        let cases: [(&str, &[(usize, &str)]); 4] = [
+            ("a: 1", &[(1, "a: 1")]),
+            ("a: 1\n---\nb: 2\nc: 3", &[(1, "a: 1"), (3, "b: 2\nc: 3")]),
+            ("---\na: 1", &[(1, ""), (2, "a: 1")]),
+            ("a: 1\n---  \n---\nb: 2", &[(1, "a: 1"), (3, ""), (4, "b: 2")]),
+        ];
+        for (text, expected) in cases.iter() {
+            let documents = split_documents(text);
+            let documents: Vec<(usize, &str)> = documents.iter().map(|(line, d)| (*line, d.as_str())).collect();
+            assert_eq!(documents, *expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn matches_plain_and_quoted_keys() {
+        let cases = [
+            ("name: web", "name", true),
+            ("name : web", "name", true),
+            ("\"name\": web", "name", true),
+            ("'name': web", "name", true),
+            ("name:", "name", true),
+            ("names: web", "name", false),
+            ("nam: web", "name", false),
+            ("- name: web", "name", false),
+        ];
+        for (content, key, expected) in cases.iter() {
+            assert_eq!(_is_key(content, key), *expected, "{} / {}", content, key);
+        }
+    }
+
+    #[test]
+    fn locates_paths_by_indentation() {
+        let text = "apiVersion: v1\nkind: Pod\nmetadata:\n  name: web\nspec:\n  # containers\n  containers:\n  - name: app\n    image: nginx\n    ports:\n    - containerPort: 80\n  - name: sidecar\n    image: envoy";
+        let lines: Vec<&str> = text.lines().collect();
+        let key = |k: &str| Segment::Key(k.to_string());
+        let cases = [
+            (vec![key("kind")], 1),
+            (vec![key("metadata"), key("name")], 3),
+            (vec![key("spec"), key("containers")], 6),
+            (vec![key("spec"), key("containers"), Segment::Index(0), key("name")], 7),
+            (vec![key("spec"), key("containers"), Segment::Index(0), key("ports"), Segment::Index(0)], 10),
+            (vec![key("spec"), key("containers"), Segment::Index(1), key("image")], 12),
+            // Falls back to the deepest ancestor found
+            (vec![key("spec"), key("containers"), Segment::Index(1), key("resources")], 11),
+            (vec![key("status")], 0),
+        ];
+        for (path, expected) in cases.iter() {
+            assert_eq!(locate(&lines, path), *expected, "{}", _path_string(path));
+        }
+    }
+
+    #[test]
+    fn validates_values_against_the_schema() {
+        let doc = document(json!({
+            "Port": {
+                "type": "object",
+                "required": ["containerPort"],
+                "properties": {
+                    "containerPort": { "type": "integer" },
+                    "targetPort": { "type": "string", "x-kubernetes-int-or-string": true },
+                },
+            },
+            "Container": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "ports": { "type": "array", "items": { "$ref": "#/definitions/Port" } },
+                    "env": { "type": "object", "additionalProperties": { "type": "string" } },
+                    "extra": { "type": "object", "x-kubernetes-preserve-unknown-fields": true },
+                },
+            },
+        }));
+        let schema = json!({ "$ref": "#/definitions/Container" });
+        let cases = vec![
+            (json!({ "name": "app", "ports": [{ "containerPort": 80, "targetPort": "http" }] }), vec![]),
+            (json!({ "name": null, "extra": { "anything": [1] } }), vec![]),
+            (json!({ "name": 1 }), vec![("name", "Expected a string but found an integer")]),
+            (json!({ "ports": {} }), vec![("ports", "Expected a list but found an object")]),
+            (
+                json!({ "ports": [{ "containerPort": 80 }, { "targetPort": 8080 }] }),
+                vec![("ports[1]", "Missing required field \"containerPort\"")],
+            ),
+            (
+                json!({ "ports": [{ "containerPort": "80", "targetPort": 1.5 }] }),
+                vec![
+                    ("ports[0].containerPort", "Expected an integer but found a string"),
+                    ("ports[0].targetPort", "Expected an integer or a string"),
+                ],
+            ),
+            (json!({ "env": { "A": "1", "B": true } }), vec![("env.B", "Expected a string but found a boolean")]),
+            (json!({ "image": "nginx" }), vec![("image", "Unknown field \"image\"")]),
+        ];
+        for (value, expected) in cases {
+            let expected: Vec<(String, String)> =
+                expected.iter().map(|(path, message)| (path.to_string(), message.to_string())).collect();
+            assert_eq!(errors(&doc, value.clone(), &schema), expected, "{}", value);
+        }
+    }
+}
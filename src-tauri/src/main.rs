@@ -5,9 +5,24 @@
 
 use crate::appmanager::AppManager;
 use crate::cache::CacheManager;
-use crate::kube::models::CommandResult;
+use crate::kube::models::{CommandResult, DeleteOptions};
 use crate::kube::{EventHolder, KNamespace, kubeclient, models};
+use crate::kube::bulk::{BulkRequest, BulkVerb};
+use crate::kube::clone::CloneRequest;
+use crate::kube::export::{self, BackupRequest};
+use crate::kube::images::ImageUpdate;
+use crate::kube::labels::{MetadataField, MetadataUpdate};
+use crate::kube::logexport::LogExportRequest;
+use crate::kube::logfilter::{FilterUpdate, LogFilter};
+use crate::kube::logs::LogOptions;
+use crate::kube::logtail::WorkloadLogRequest;
+use crate::kube::nodes::DrainOptions;
+use crate::kube::rollout::WorkloadKind;
 use crate::store::{DataStoreManager, PKEY_KUBECONFIG_FILE_LOCATION, Preference};
+use crate::store::alerts::{self, AlertRule};
+use crate::store::audit::AuditQuery;
+use crate::store::logs::LogSearchQuery;
+use crate::store::templates::{self, Template};
 use crate::task::TaskManager;
 use ::kube::api::Object;
 use regex::Regex;
@@ -34,6 +49,7 @@ mod task;
 mod utils;
 mod menu;
 mod license;
+mod protection;
 
 #[macro_use]
 extern crate log;
@@ -91,6 +107,17 @@ fn execute_sync_command(
     const GET_RESOURCE_DEFINITION: &str = "get_resource_definition";
     const EDIT_RESOURCE: &str = "edit_resource";
     const GET_RESOURCE_TEMPLATE: &str = "get_resource_template";
+    const ROLLOUT_STATUS: &str = "rollout_status";
+    const GET_CONTEXT_PROTECTION: &str = "get_context_protection";
+    const LIST_TEMPLATES: &str = "list_templates";
+    const SAVE_TEMPLATE: &str = "save_template";
+    const DELETE_TEMPLATE: &str = "delete_template";
+    const RENDER_TEMPLATE: &str = "render_template";
+    const SET_CONTEXT_PROTECTION: &str = "set_context_protection";
+    const SET_PROTECTION_PATTERN: &str = "set_protection_pattern";
+    const LIST_ALERT_RULES: &str = "list_alert_rules";
+    const SAVE_ALERT_RULE: &str = "save_alert_rule";
+    const DELETE_ALERT_RULE: &str = "delete_alert_rule";
 
     let stateHolder = &mut appmanager.0.lock().unwrap();
 
@@ -100,6 +127,11 @@ fn execute_sync_command(
     let cmd_hldr: CommandHolder = serde_json::from_str(commandstr).unwrap();
     let mut res = CommandResult::new();
     res.command = cmd_hldr.command.clone();
+    let guarded = protection::guard(&window, &stateHolder.dsmanager, &stateHolder.kubemanager, &cmd_hldr.command, &cmd_hldr.args);
+    if let Err(denied) = guarded {
+        res.data = serde_json::to_string(&denied).unwrap();
+        return serde_json::to_string(&res).unwrap();
+    }
     if cmd_hldr.command == GET_PODS_FOR_DEPLOYMENT {
         let ns = cmd_hldr.args.get("ns").unwrap();
         let deployment = cmd_hldr.args.get("deployment").unwrap();
@@ -129,10 +161,15 @@ fn execute_sync_command(
         let ns = cmd_hldr.args.get("ns").unwrap();
         let name = cmd_hldr.args.get("name").unwrap();
         let kind = cmd_hldr.args.get("kind").unwrap();
+        let export = cmd_hldr.args.get("export").map(|v| v == "true").unwrap_or(false);
         let d = &stateHolder.kubemanager.get_resource_definition(ns, name, kind);
         match d {
             Some(data) => {
-                res.data = serde_yaml::to_string(&data).unwrap();
+                let mut value = serde_json::to_value(&data).unwrap();
+                if export {
+                    export::clean_for_export(&mut value);
+                }
+                res.data = serde_yaml::to_string(&value).unwrap();
             }
             None => {
                 utils::send_error(&window, "Resource not found");
@@ -143,16 +180,95 @@ fn execute_sync_command(
         let kind = cmd_hldr.args.get("kind").unwrap();
         let name = cmd_hldr.args.get("name").unwrap();
         let resource_str = cmd_hldr.args.get("resource").unwrap();
-        let d = &stateHolder.kubemanager.edit_resource(ns, resource_str, name, kind);
+        let validate = cmd_hldr.args.get("validate").map(|v| v != "false").unwrap_or(true);
+        let d = &stateHolder.kubemanager.edit_resource(&window, ns, resource_str, name, kind, validate);
         if *d {
             res.data = "Success".to_string();
+            if WorkloadKind::from_kind(kind).is_some() {
+                let km = stateHolder.kubemanager.clone();
+                let (ns, kind, name) = (ns.to_string(), kind.to_string(), name.to_string());
+                let _ = thread::spawn(move || {
+                    km.rollout_status(&window, &ns, &kind, &name, None, ROLLOUT_STATUS);
+                });
+            }
         }else{
             utils::send_error(&window, "Failed to edit resource");
         }
     } else if cmd_hldr.command == GET_RESOURCE_TEMPLATE {
         let kind = cmd_hldr.args.get("kind").unwrap();
-        let tx = _get_template(kind);
-        res.data = tx.to_string();
+        res.data = _get_template(kind);
+    } else if cmd_hldr.command == LIST_TEMPLATES {
+        match templates::list() {
+            Ok(list) => res.data = serde_json::to_string(&list).unwrap(),
+            Err(err) => utils::send_error(&window, &format!("Failed to load templates. Reason: {}", err)),
+        }
+    } else if cmd_hldr.command == SAVE_TEMPLATE {
+        let template = cmd_hldr.args.get("template").unwrap();
+        let saved = serde_json::from_str::<Template>(template)
+            .map_err(|e| format!("Invalid template: {}", e))
+            .and_then(|template| templates::save(&template));
+        match saved {
+            Ok(()) => res.data = "Success".to_string(),
+            Err(err) => utils::send_error(&window, &format!("Failed to save template. Reason: {}", err)),
+        }
+    } else if cmd_hldr.command == DELETE_TEMPLATE {
+        let name = cmd_hldr.args.get("name").unwrap();
+        match templates::delete(name) {
+            Ok(()) => res.data = "Success".to_string(),
+            Err(err) => utils::send_error(&window, &format!("Failed to delete template. Reason: {}", err)),
+        }
+    } else if cmd_hldr.command == LIST_ALERT_RULES {
+        match alerts::list() {
+            Ok(list) => res.data = serde_json::to_string(&list).unwrap(),
+            Err(err) => utils::send_error(&window, &format!("Failed to load alert rules. Reason: {}", err)),
+        }
+    } else if cmd_hldr.command == SAVE_ALERT_RULE {
+        let rule = cmd_hldr.args.get("rule").unwrap();
+        let saved = serde_json::from_str::<AlertRule>(rule)
+            .map_err(|e| format!("Invalid alert rule: {}", e))
+            .and_then(|mut rule| {
+                if rule.context.is_empty() {
+                    rule.context = stateHolder.kubemanager.context();
+                }
+                alerts::save(&rule)
+            });
+        match saved {
+            Ok(rule) => {
+                res.data = serde_json::to_string(&rule).unwrap();
+                if rule.enabled {
+                    _start_log_alert(stateHolder, window, rule);
+                } else {
+                    stateHolder.taskmanager.stop_alert_stream(rule.id);
+                }
+            },
+            Err(err) => utils::send_error(&window, &format!("Failed to save alert rule. Reason: {}", err)),
+        }
+    } else if cmd_hldr.command == DELETE_ALERT_RULE {
+        match cmd_hldr.args.get("id").and_then(|id| id.parse::<i64>().ok()) {
+            Some(id) => match alerts::delete(id) {
+                Ok(()) => {
+                    stateHolder.taskmanager.stop_alert_stream(id);
+                    res.data = "Success".to_string();
+                },
+                Err(err) => utils::send_error(&window, &format!("Failed to delete alert rule. Reason: {}", err)),
+            },
+            None => utils::send_error(&window, "A valid alert rule id is required"),
+        }
+    } else if cmd_hldr.command == RENDER_TEMPLATE {
+        let name = cmd_hldr.args.get("name").unwrap();
+        let values: HashMap<String, String> = cmd_hldr
+            .args
+            .get("values")
+            .and_then(|v| serde_json::from_str(v).ok())
+            .unwrap_or_default();
+        let rendered = templates::get(name)
+            .map_err(|e| e.to_string())
+            .and_then(|t| t.ok_or_else(|| format!("Template {} not found", name)))
+            .and_then(|t| t.render(&values));
+        match rendered {
+            Ok(yaml) => res.data = yaml,
+            Err(err) => utils::send_error(&window, &format!("Failed to render template. Reason: {}", err)),
+        }
     } else if cmd_hldr.command == GET_ALL_CLUSTER_CONTEXTS {
         let clusters = kube::get_clusters(&window);
         res.data = serde_json::to_string(&clusters).unwrap();
@@ -179,10 +295,56 @@ fn execute_sync_command(
     } else if cmd_hldr.command == SAVE_PREFERENCE {
         let key = cmd_hldr.args.get("key").unwrap();
         let value = cmd_hldr.args.get("value").unwrap();
-        let pref = Preference{key: key.to_string(), value: value.to_string()};
-        stateHolder.dsmanager.upsert(pref);
-        if key == PKEY_KUBECONFIG_FILE_LOCATION {
-            stateHolder.kubemanager.set_kubeconfig_file(value);
+        if protection::is_protection_key(key) {
+            utils::send_error(&window, &format!("{} can only be changed from the protection settings", key));
+        } else {
+            let pref = Preference{key: key.to_string(), value: value.to_string()};
+            stateHolder.dsmanager.upsert(pref);
+            if key == PKEY_KUBECONFIG_FILE_LOCATION {
+                stateHolder.kubemanager.set_kubeconfig_file(value);
+            }
+        }
+    } else if cmd_hldr.command == GET_CONTEXT_PROTECTION {
+        let context = match cmd_hldr.args.get("context") {
+            Some(context) => context.to_string(),
+            None => stateHolder.kubemanager.context(),
+        };
+        let protection = protection::protection_for(&stateHolder.dsmanager, &context);
+        res.data = serde_json::to_string(&protection).unwrap();
+    } else if cmd_hldr.command == SET_CONTEXT_PROTECTION {
+        let context = cmd_hldr.args.get("context").unwrap();
+        let level = cmd_hldr.args.get("level").unwrap();
+        match protection::ProtectionLevel::parse(level) {
+            Some(level) => {
+                match protection::change_protection(&window, &stateHolder.dsmanager, context, level, &cmd_hldr.args) {
+                    Ok(protection) => res.data = serde_json::to_string(&protection).unwrap(),
+                    Err(denied) => res.data = serde_json::to_string(&denied).unwrap(),
+                }
+            },
+            None => utils::send_error(&window, &format!("Unknown protection level: {}", level)),
+        }
+    } else if cmd_hldr.command == SET_PROTECTION_PATTERN {
+        let pattern = cmd_hldr.args.get("pattern").map(|p| p.as_str()).unwrap_or_default();
+        let level = cmd_hldr.args.get("level").map(|l| l.as_str()).unwrap_or("confirm");
+        match (protection::validate_pattern(pattern), protection::ProtectionLevel::parse(level)) {
+            (Err(err), _) => utils::send_error(&window, &err),
+            (Ok(()), None) => utils::send_error(&window, &format!("Unknown protection level: {}", level)),
+            (Ok(()), Some(level)) => {
+                let changed = protection::change_pattern(
+                    &window,
+                    &stateHolder.dsmanager,
+                    &stateHolder.kubemanager,
+                    pattern,
+                    level,
+                    &cmd_hldr.args,
+                );
+                if let Err(denied) = changed {
+                    res.data = serde_json::to_string(&denied).unwrap();
+                } else {
+                    let protection = protection::protection_for(&stateHolder.dsmanager, &stateHolder.kubemanager.context());
+                    res.data = serde_json::to_string(&protection).unwrap();
+                }
+            },
         }
     } else if cmd_hldr.command == GET_PREFERENCES {
         let keys = cmd_hldr.args.keys();
@@ -204,21 +366,17 @@ fn execute_sync_command(
     serde_json::to_string(&res).unwrap()
 }
 
-fn _get_template(kind: &str) -> &str {
-    if kind.to_lowercase().eq("namespace") {
-        include_str!("./kube/yaml/ns.yaml")
-    } else if kind.to_lowercase().eq("configmap") {
-        include_str!("./kube/yaml/configmap.yaml")
-    } else if kind.to_lowercase().eq("deployment") {
-        include_str!("./kube/yaml/deployment.yaml")
-    } else if kind.to_lowercase().eq("service") {
-        include_str!("./kube/yaml/service.yaml")
-    } else if kind.to_lowercase().eq("pod") {
-        include_str!("./kube/yaml/pod.yaml")
-    } else if kind.to_lowercase().eq("replicaset") {
-        include_str!("./kube/yaml/replicaset.yaml")
-    } else {
-        return ""
+fn _get_template(kind: &str) -> String {
+    match templates::for_kind(kind) {
+        Ok(Some(template)) => template.sample().unwrap_or_else(|err| {
+            error!("Failed to render template for {}: {}", kind, err);
+            "".to_string()
+        }),
+        Ok(None) => "".to_string(),
+        Err(err) => {
+            error!("Failed to load template for {}: {}", kind, err);
+            "".to_string()
+        }
     }
 }
 
@@ -232,7 +390,12 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
     const GET_METRICS_FOR_DEPLOYMENT: &str = "get_metrics_for_deployment";
     const RESTART_DEPLOYMENTS: &str = "restart_deployments";
     const TAIL_LOGS_FOR_POD: &str = "tail_logs_for_pod";
+    const TAIL_LOGS_FOR_WORKLOAD: &str = "tail_logs_for_workload";
+    const SET_LOG_FILTER: &str = "set_log_filter";
     const GET_LOGS_FOR_POD: &str = "get_logs_for_pod";
+    const GET_POD_CONTAINERS: &str = "get_pod_containers";
+    const EXPORT_LOGS: &str = "export_logs";
+    const SEARCH_LOGS: &str = "search_logs";
     const GET_ENVIRONMENT_VARIABLES_FOR_POD: &str = "get_environment_variables_for_pod";
     const STREAM_METRICS_FOR_POD: &str = "stream_metrics_for_pod";
     const STREAM_METRICS_FOR_DEPLOYMENT: &str = "stream_metrics_for_deployment";
@@ -243,6 +406,28 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
     const APP_START: &str = "app_start";
     const CREATE_RESOURCE: &str = "apply_resource";
     const DELETE_RESOURCE: &str = "delete_resource";
+    const PREVIEW_DELETE: &str = "preview_delete";
+    const VALIDATE_RESOURCE: &str = "validate_resource";
+    const BACKUP_NAMESPACE: &str = "backup_namespace";
+    const CLONE_RESOURCES: &str = "clone_resources";
+    const GET_ROLLOUT_HISTORY: &str = "get_rollout_history";
+    const ROLLBACK_ROLLOUT: &str = "rollback_rollout";
+    const ROLLOUT_STATUS: &str = "rollout_status";
+    const SCALE_WORKLOAD: &str = "scale_workload";
+    const PAUSE_ROLLOUT: &str = "pause_rollout";
+    const RESUME_ROLLOUT: &str = "resume_rollout";
+    const CORDON_NODE: &str = "cordon_node";
+    const UNCORDON_NODE: &str = "uncordon_node";
+    const DRAIN_NODE: &str = "drain_node";
+    const SET_LABELS: &str = "set_labels";
+    const SET_ANNOTATIONS: &str = "set_annotations";
+    const SET_IMAGE: &str = "set_image";
+    const GET_AUDIT_LOG: &str = "get_audit_log";
+    const UNDO_AUDIT_RECORD: &str = "undo_audit_record";
+    const TRIGGER_CRON_JOB: &str = "trigger_cron_job";
+    const SUSPEND_CRON_JOB: &str = "suspend_cron_job";
+    const RESUME_CRON_JOB: &str = "resume_cron_job";
+    const GET_CRON_JOB_SCHEDULES: &str = "get_cron_job_schedules";
 
     let stateHolder = &mut appmanager.0.lock().unwrap();
 
@@ -250,6 +435,9 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
 
     debug!("Current cluster: {}", current_cluster);
     let cmd_hldr: CommandHolder = serde_json::from_str(commandstr).unwrap();
+    if protection::guard(&window, &stateHolder.dsmanager, &stateHolder.kubemanager, &cmd_hldr.command, &cmd_hldr.args).is_err() {
+        return;
+    }
     if cmd_hldr.command == GET_ALL_NS {
         let pref = stateHolder.dsmanager.query(store::CUSTOM_NS_LIST.to_string(), None);
         let kubemanager = &stateHolder.kubemanager;
@@ -284,7 +472,8 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
                 kind = skind;
             }
             let ns = cmd_hldr.args.get("ns");
-            let _ = km.create_resource(&window, resource_str, kind, ns, CREATE_RESOURCE);
+            let validate = cmd_hldr.args.get("validate").map(|v| v != "false").unwrap_or(true);
+            let _ = km.create_resource(&window, resource_str, kind, ns, validate, CREATE_RESOURCE);
         });
 
     } else if cmd_hldr.command == DELETE_RESOURCE {
@@ -297,7 +486,10 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
                 ns = sns;
             }
             let kind = cmd_hldr.args.get("kind").unwrap();
-            let _ = km.delete_resource(&window, ns, name, kind, DELETE_RESOURCE);
+            match DeleteOptions::from_args(&cmd_hldr.args) {
+                Ok(options) => km.delete_resource(&window, ns, name, kind, options, DELETE_RESOURCE),
+                Err(err) => utils::send_error(&window, &err),
+            }
         });
 
     } else if cmd_hldr.command == GET_RESOURCE_WITH_METRICS {
@@ -348,6 +540,191 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
             let deployment =  cmd_hldr.args.get("deployment").unwrap();
             km.restart_deployment(&window, namespace, deployment, RESTART_DEPLOYMENTS);
         });
+    } else if cmd_hldr.command == GET_ROLLOUT_HISTORY {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            km.get_rollout_history(&window, ns, kind, name, GET_ROLLOUT_HISTORY);
+        });
+    } else if cmd_hldr.command == ROLLBACK_ROLLOUT {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let mut revision = 0;
+            if let Some(rev) = cmd_hldr.args.get("revision") {
+                revision = rev.parse().unwrap_or(0);
+            }
+            km.rollback_rollout(&window, ns, kind, name, revision, ROLLBACK_ROLLOUT);
+        });
+    } else if cmd_hldr.command == ROLLOUT_STATUS {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let timeout = cmd_hldr.args.get("timeout").and_then(|t| t.parse().ok());
+            km.rollout_status(&window, ns, kind, name, timeout, ROLLOUT_STATUS);
+        });
+    } else if cmd_hldr.command == SCALE_WORKLOAD {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let replicas = cmd_hldr.args.get("replicas").unwrap();
+            match replicas.parse() {
+                Ok(replicas) => km.scale_workload(&window, ns, kind, name, replicas, SCALE_WORKLOAD),
+                Err(_) => utils::send_error(&window, "Replicas must be a number"),
+            }
+        });
+    } else if cmd_hldr.command == PAUSE_ROLLOUT || cmd_hldr.command == RESUME_ROLLOUT {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let name = cmd_hldr.args.get("deployment").unwrap();
+            let paused = cmd_hldr.command == PAUSE_ROLLOUT;
+            km.set_rollout_paused(&window, ns, name, paused, &cmd_hldr.command);
+        });
+    } else if cmd_hldr.command == VALIDATE_RESOURCE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let resource_str = cmd_hldr.args.get("resource").unwrap();
+            km.validate_resource(&window, resource_str, VALIDATE_RESOURCE);
+        });
+    } else if cmd_hldr.command == BACKUP_NAMESPACE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match BackupRequest::from_args(&cmd_hldr.args) {
+                Ok(request) => km.backup_namespace(&window, request, BACKUP_NAMESPACE),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == CLONE_RESOURCES {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match CloneRequest::from_args(&cmd_hldr.args) {
+                Ok(request) => km.clone_resources(&window, request, CLONE_RESOURCES),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == PREVIEW_DELETE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let mut ns = "";
+            if let Some(sns) = cmd_hldr.args.get("ns") {
+                ns = sns;
+            }
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let orphan = cmd_hldr.args.get("propagation").map(|p| p.eq_ignore_ascii_case("orphan")).unwrap_or(false);
+            km.preview_delete(&window, ns, kind, name, orphan, PREVIEW_DELETE);
+        });
+    } else if cmd_hldr.command == CORDON_NODE || cmd_hldr.command == UNCORDON_NODE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let node = cmd_hldr.args.get("node").unwrap();
+            let unschedulable = cmd_hldr.command == CORDON_NODE;
+            km.set_node_unschedulable(&window, node, unschedulable, &cmd_hldr.command);
+        });
+    } else if cmd_hldr.command == DRAIN_NODE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let node = cmd_hldr.args.get("node").unwrap();
+            let options = DrainOptions::from_args(&cmd_hldr.args);
+            km.drain_node(&window, node, options, DRAIN_NODE);
+        });
+    } else if cmd_hldr.command == SET_LABELS || cmd_hldr.command == SET_ANNOTATIONS {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let mut ns = "";
+            if let Some(sns) = cmd_hldr.args.get("ns") {
+                ns = sns;
+            }
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let field = if cmd_hldr.command == SET_LABELS { MetadataField::Labels } else { MetadataField::Annotations };
+            match MetadataUpdate::from_args(field, &cmd_hldr.args) {
+                Ok(update) => km.set_metadata(&window, ns, kind, name, update, &cmd_hldr.command),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == SET_IMAGE {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let kind = cmd_hldr.args.get("kind").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            match ImageUpdate::from_args(&cmd_hldr.args) {
+                Ok(update) => km.set_image(&window, ns, kind, name, update, SET_IMAGE),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == GET_AUDIT_LOG {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let query = AuditQuery::from_args(&cmd_hldr.args);
+            km.get_audit_log(&window, query, GET_AUDIT_LOG);
+        });
+    } else if cmd_hldr.command == UNDO_AUDIT_RECORD {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match cmd_hldr.args.get("id").and_then(|id| id.parse().ok()) {
+                Some(id) => km.undo_audit_record(&window, id, UNDO_AUDIT_RECORD),
+                None => utils::send_error(&window, "A valid audit record id is required"),
+            }
+        });
+    } else if cmd_hldr.command == TRIGGER_CRON_JOB {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            km.trigger_cron_job(&window, ns, name, TRIGGER_CRON_JOB);
+        });
+    } else if cmd_hldr.command == SUSPEND_CRON_JOB || cmd_hldr.command == RESUME_CRON_JOB {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let name = cmd_hldr.args.get("name").unwrap();
+            let suspend = cmd_hldr.command == SUSPEND_CRON_JOB;
+            km.set_cron_job_suspended(&window, ns, name, suspend, &cmd_hldr.command);
+        });
+    } else if cmd_hldr.command == GET_CRON_JOB_SCHEDULES {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            km.get_cron_job_schedules(&window, ns, GET_CRON_JOB_SCHEDULES);
+        });
+    } else if let Some(verb) = BulkVerb::from_command(&cmd_hldr.command) {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match BulkRequest::from_args(&cmd_hldr.args) {
+                Ok(request) => km.bulk_operation(&window, verb, request, &cmd_hldr.command),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
     } else if cmd_hldr.command == TAIL_LOGS_FOR_POD {
         let (tx, rx): (Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         let kubemanager = &stateHolder.kubemanager;
@@ -355,10 +732,25 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
         let _ = thread::spawn(move || {
             let ns = cmd_hldr.args.get("ns").unwrap();
             let podname = cmd_hldr.args.get("pod").unwrap();
-            km.tail_logs_for_pod(window, &podname, &ns, &rx);
+            let options = LogOptions::from_args(&cmd_hldr.args, Some(1));
+            match options.and_then(|options| LogFilter::from_args(&cmd_hldr.args).map(|filter| (options, filter))) {
+                Ok((options, filter)) => km.tail_logs_for_pod(window, &podname, &ns, &options, filter, &rx),
+                Err(err) => utils::send_error(&window, &err),
+            }
             debug!("Tail of logs initiated");
         });
         stateHolder.taskmanager.add_logs_stream(tx);
+    } else if cmd_hldr.command == TAIL_LOGS_FOR_WORKLOAD {
+        let (tx, rx): (Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match WorkloadLogRequest::from_args(&cmd_hldr.args) {
+                Ok(request) => km.tail_logs_for_workload(window, request, &rx),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+        stateHolder.taskmanager.add_logs_stream(tx);
     }  else if cmd_hldr.command == OPEN_SHELL {
         let (tx, rx): (Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         let kubemanager = &stateHolder.kubemanager;
@@ -370,6 +762,12 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
             debug!("Pod shell initiated");
         });
         stateHolder.taskmanager.add_shell_stream(tx);
+    } else if cmd_hldr.command == SET_LOG_FILTER {
+        let update = FilterUpdate::from_args(&cmd_hldr.args);
+        match update.and_then(|update| update.filter().map(|_| update)) {
+            Ok(update) => stateHolder.taskmanager.send_to_logs(&update.control_message()),
+            Err(err) => utils::send_error(&window, &err),
+        }
     }  else if cmd_hldr.command == SEND_TO_SHELL {
         let (tx, rx): (Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         let ns = cmd_hldr.args.get("ns").unwrap();
@@ -380,7 +778,36 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
         let _ = thread::spawn(move || {
             let ns = cmd_hldr.args.get("ns").unwrap();
             let podname = cmd_hldr.args.get("pod").unwrap();
-            kube::get_logs_for_pod(window, &current_cluster, &podname, &ns);
+            match LogOptions::from_args(&cmd_hldr.args, Some(100)) {
+                Ok(options) => kube::get_logs_for_pod(window, &current_cluster, &podname, &ns, &options),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == GET_POD_CONTAINERS {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            let ns = cmd_hldr.args.get("ns").unwrap();
+            let podname = cmd_hldr.args.get("pod").unwrap();
+            km.get_pod_containers(&window, ns, podname, GET_POD_CONTAINERS);
+        });
+    } else if cmd_hldr.command == EXPORT_LOGS {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match LogExportRequest::from_args(&cmd_hldr.args) {
+                Ok(request) => km.export_logs(&window, request, EXPORT_LOGS),
+                Err(err) => utils::send_error(&window, &err),
+            }
+        });
+    } else if cmd_hldr.command == SEARCH_LOGS {
+        let kubemanager = &stateHolder.kubemanager;
+        let km = kubemanager.clone();
+        let _ = thread::spawn(move || {
+            match LogSearchQuery::from_args(&cmd_hldr.args) {
+                Ok(query) => km.search_logs(&window, query, SEARCH_LOGS),
+                Err(err) => utils::send_error(&window, &err),
+            }
         });
     } else if cmd_hldr.command == GET_ENVIRONMENT_VARIABLES_FOR_POD {
         let kubemanager = &stateHolder.kubemanager;
@@ -423,6 +850,14 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
         debug!("App started");
         let license = stateHolder.dsmanager.query(store::LICENSE_STRING_KEY.to_string(), None);
         let eula = stateHolder.dsmanager.query(store::KEY_EULA_ACCEPT.to_string(), None);
+        match alerts::list() {
+            Ok(rules) => {
+                for rule in rules.into_iter().filter(|r| r.enabled) {
+                    _start_log_alert(stateHolder, window.clone(), rule);
+                }
+            },
+            Err(err) => error!("Failed to load alert rules: {}", err),
+        }
         let hndl = thread::spawn(move || {
             let current = get_current_cluster();
             let clusters: Vec<KCluster> = get_clusters(current);
@@ -440,6 +875,18 @@ fn execute_command(window: Window, commandstr: &str, appmanager: State<Singleton
     }
 }
 
+/// Runs an alert rule on a background tail that stays up while the app is open, replacing the
+/// tail of an earlier version of the rule.
+fn _start_log_alert(state: &mut AppManager, window: Window, rule: AlertRule) {
+    let (tx, rx): (Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
+    let km = state.kubemanager.clone();
+    let id = rule.id;
+    let _ = thread::spawn(move || {
+        km.watch_log_alert(window, rule, &rx);
+    });
+    state.taskmanager.add_alert_stream(id, tx);
+}
+
 fn get_custom_ns_list(ns_string: Option<String>) -> Vec<KNamespace>{
     let mut custom_ns = Vec::new();
     match ns_string {
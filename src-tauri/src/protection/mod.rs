@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use regex::Regex;
+use tauri::Window;
+use crate::kube::kubeclient::KubeClientManager;
+use crate::store::{DataStoreManager, Preference};
+
+pub const PROTECTION_CHANNEL: &str = "app::protection_required";
+
+/// JSON map of context name to protection level.
+pub const PKEY_CONTEXT_PROTECTION: &str = "PKEY_CONTEXT_PROTECTION";
+/// Contexts whose name matches this regex are protected without an explicit setting.
+pub const PKEY_PROTECTION_PATTERN: &str = "PKEY_PROTECTION_PATTERN";
+pub const PKEY_PROTECTION_PATTERN_LEVEL: &str = "PKEY_PROTECTION_PATTERN_LEVEL";
+/// Preferences that may only be changed through the protection commands, which ask for
+/// confirmation before protection is lowered.
+const PROTECTION_KEYS: [&str; 3] = [PKEY_CONTEXT_PROTECTION, PKEY_PROTECTION_PATTERN, PKEY_PROTECTION_PATTERN_LEVEL];
+
+/// Commands that change the cluster. Anything not listed here is treated as a read.
+const MUTATING_COMMANDS: &[&str] = &[
+    "apply_resource",
+    "edit_resource",
+    "delete_resource",
+    "restart_deployments",
+    "rollback_rollout",
+    "scale_workload",
+    "pause_rollout",
+    "resume_rollout",
+    "cordon_node",
+    "uncordon_node",
+    "drain_node",
+    "set_labels",
+    "set_annotations",
+    "set_image",
+    "trigger_cron_job",
+    "suspend_cron_job",
+    "resume_cron_job",
+    "undo_audit_record",
+    "bulk_delete",
+    "bulk_restart",
+    "bulk_scale",
+    "bulk_label",
+    "bulk_annotate",
+    "clone_resources",
+    "open_shell",
+];
+
+/// Commands whose handlers pass `dry_run` through to the API server. Only these may skip the
+/// protection check for a dry run; any other command would simply ignore the flag and write.
+const DRY_RUN_COMMANDS: &[&str] = &["clone_resources"];
+
+/// Commands that write to a context other than the current one name it in this argument.
+const TARGET_CONTEXT_ARG: &str = "target_context";
+
+/// Argument keys that name the target of a command, in order of preference.
+const TARGET_ARGS: [&str; 4] = ["name", "deployment", "node", "pod"];
+
+/// Ordered from the least to the most protected.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtectionLevel {
+    Normal,
+    Confirm,
+    ReadOnly,
+}
+
+impl ProtectionLevel {
+    pub fn parse(level: &str) -> Option<ProtectionLevel> {
+        match level.to_lowercase().as_str() {
+            "normal" => Some(ProtectionLevel::Normal),
+            "confirm" => Some(ProtectionLevel::Confirm),
+            "read_only" | "readonly" => Some(ProtectionLevel::ReadOnly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ContextProtection {
+    pub(crate) context: String,
+    pub(crate) level: ProtectionLevel,
+    /// True when the level comes from the name pattern rather than an explicit setting.
+    pub(crate) from_pattern: bool,
+}
+
+/// Sent to the frontend when a command is held back, so it can either explain the read-only
+/// context or prompt for the name to type and resend the command with `confirm_name`.
+#[derive(Clone, serde::Serialize)]
+pub struct ProtectionDenied {
+    pub(crate) command: String,
+    pub(crate) context: String,
+    pub(crate) level: ProtectionLevel,
+    pub(crate) expected: Option<String>,
+    pub(crate) message: String,
+}
+
+pub fn is_mutating(command: &str) -> bool {
+    MUTATING_COMMANDS.contains(&command)
+}
+
+/// Reads the `dry_run` argument. Handlers in `DRY_RUN_COMMANDS` parse it through here too, so a
+/// request let through as a dry run is always sent to the server as one.
+pub fn is_protection_key(key: &str) -> bool {
+    PROTECTION_KEYS.contains(&key)
+}
+
+pub fn is_dry_run(args: &HashMap<String, String>) -> bool {
+    args.get("dry_run").map(|v| v == "true").unwrap_or(false)
+}
+
+pub fn protection_for(dsmanager: &DataStoreManager, context: &str) -> ContextProtection {
+    let explicit = dsmanager
+        .query(PKEY_CONTEXT_PROTECTION.to_string(), None)
+        .and_then(|levels| serde_json::from_str::<HashMap<String, String>>(&levels).ok())
+        .and_then(|levels| levels.get(context).and_then(|l| ProtectionLevel::parse(l)));
+    if let Some(level) = explicit {
+        return ContextProtection { context: context.to_string(), level, from_pattern: false };
+    }
+    let pattern = dsmanager.query(PKEY_PROTECTION_PATTERN.to_string(), None).unwrap_or_default();
+    if !pattern.trim().is_empty() {
+        match Regex::new(&format!("^(?:{})$", pattern)) {
+            Ok(re) if re.is_match(context) => {
+                let level = dsmanager
+                    .query(PKEY_PROTECTION_PATTERN_LEVEL.to_string(), None)
+                    .and_then(|l| ProtectionLevel::parse(&l))
+                    .unwrap_or(ProtectionLevel::Confirm);
+                return ContextProtection { context: context.to_string(), level, from_pattern: true };
+            },
+            Ok(_) => {},
+            Err(err) => warn!("Ignoring invalid protection pattern {}: {}", pattern, err),
+        }
+    }
+    ContextProtection { context: context.to_string(), level: ProtectionLevel::Normal, from_pattern: false }
+}
+
+/// Sets the level of a context. Lowering it, including below the level the name pattern gives,
+/// needs the context name typed back as `confirm_name`.
+pub fn change_protection(
+    window: &Window,
+    dsmanager: &DataStoreManager,
+    context: &str,
+    level: ProtectionLevel,
+    args: &HashMap<String, String>,
+) -> Result<ContextProtection, ProtectionDenied> {
+    let current = protection_for(dsmanager, context);
+    if level < current.level {
+        let what = format!("context {}", context);
+        _confirm_lowering(window, "set_context_protection", &current, context, &what, args)?;
+    }
+    set_protection(dsmanager, context, level);
+    Ok(protection_for(dsmanager, context))
+}
+
+/// Replaces the name pattern. Changing or clearing an existing pattern, or lowering its level,
+/// can unprotect contexts, so it needs the current pattern typed back as `confirm_name`.
+pub fn change_pattern(
+    window: &Window,
+    dsmanager: &DataStoreManager,
+    kubemanager: &KubeClientManager,
+    pattern: &str,
+    level: ProtectionLevel,
+    args: &HashMap<String, String>,
+) -> Result<(), ProtectionDenied> {
+    let current_pattern = dsmanager.query(PKEY_PROTECTION_PATTERN.to_string(), None).unwrap_or_default();
+    let current_level = dsmanager
+        .query(PKEY_PROTECTION_PATTERN_LEVEL.to_string(), None)
+        .and_then(|l| ProtectionLevel::parse(&l))
+        .unwrap_or(ProtectionLevel::Confirm);
+    let current_pattern = current_pattern.trim();
+    if !current_pattern.is_empty() && (pattern.trim() != current_pattern || level < current_level) {
+        let current = protection_for(dsmanager, &kubemanager.context());
+        let what = format!("contexts matching {}", current_pattern);
+        _confirm_lowering(window, "set_protection_pattern", &current, current_pattern, &what, args)?;
+    }
+    dsmanager.upsert(Preference { key: PKEY_PROTECTION_PATTERN.to_string(), value: pattern.trim().to_string() });
+    let value = serde_json::to_value(level).unwrap();
+    dsmanager.upsert(Preference {
+        key: PKEY_PROTECTION_PATTERN_LEVEL.to_string(),
+        value: value.as_str().unwrap_or_default().to_string(),
+    });
+    Ok(())
+}
+
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.trim().is_empty() {
+        return Ok(());
+    }
+    Regex::new(&format!("^(?:{})$", pattern.trim()))
+        .map(|_| ())
+        .map_err(|e| format!("Invalid protection pattern: {}", e))
+}
+
+fn _confirm_lowering(
+    window: &Window,
+    command: &str,
+    protection: &ContextProtection,
+    expected: &str,
+    what: &str,
+    args: &HashMap<String, String>,
+) -> Result<(), ProtectionDenied> {
+    let typed = args.get("confirm_name").map(|n| n.trim()).unwrap_or_default();
+    if typed == expected {
+        return Ok(());
+    }
+    let denied = ProtectionDenied {
+        command: command.to_string(),
+        context: protection.context.clone(),
+        level: protection.level,
+        expected: Some(expected.to_string()),
+        message: format!("Type {} to lower the protection of {}", expected, what),
+    };
+    info!("Blocked {}: {}", command, denied.message);
+    window.emit(PROTECTION_CHANNEL, denied.clone()).unwrap();
+    Err(denied)
+}
+
+pub fn set_protection(dsmanager: &DataStoreManager, context: &str, level: ProtectionLevel) -> Option<bool> {
+    let mut levels: HashMap<String, String> = dsmanager
+        .query(PKEY_CONTEXT_PROTECTION.to_string(), None)
+        .and_then(|levels| serde_json::from_str(&levels).ok())
+        .unwrap_or_default();
+    let value = serde_json::to_value(level).unwrap();
+    levels.insert(context.to_string(), value.as_str().unwrap_or_default().to_string());
+    dsmanager.upsert(Preference {
+        key: PKEY_CONTEXT_PROTECTION.to_string(),
+        value: serde_json::to_string(&levels).unwrap(),
+    })
+}
+
+/// Checks a command against the protection of the current context before anything reaches the
+/// cluster. In confirm mode the typed resource or context name has to come back as `confirm_name`.
+pub fn check_command(
+    protection: &ContextProtection,
+    command: &str,
+    args: &HashMap<String, String>,
+) -> Result<(), ProtectionDenied> {
+    // A server side dry run changes nothing, so it is allowed even in read-only contexts
+    if !is_mutating(command) || (DRY_RUN_COMMANDS.contains(&command) && is_dry_run(args)) {
+        return Ok(());
+    }
+    let mut denied = ProtectionDenied {
+        command: command.to_string(),
+        context: protection.context.clone(),
+        level: protection.level,
+        expected: None,
+        message: String::new(),
+    };
+    match protection.level {
+        ProtectionLevel::Normal => Ok(()),
+        ProtectionLevel::ReadOnly => {
+            denied.message = format!("Context {} is read-only. {} is not allowed", protection.context, command);
+            Err(denied)
+        },
+        ProtectionLevel::Confirm => {
+            let typed = args.get("confirm_name").map(|n| n.trim()).unwrap_or_default();
+            let target = TARGET_ARGS.iter().find_map(|key| args.get(*key));
+            let confirmed = !typed.is_empty()
+                && (typed == protection.context || target.map(|t| t == typed).unwrap_or(false));
+            if confirmed {
+                return Ok(());
+            }
+            let expected = target.cloned().unwrap_or_else(|| protection.context.clone());
+            denied.message = format!("Context {} is protected. Type {} to confirm {}", protection.context, expected, command);
+            denied.expected = Some(expected);
+            Err(denied)
+        }
+    }
+}
+
+/// Runs the protection check for a command and tells the frontend when it is held back.
+pub fn guard(
+    window: &Window,
+    dsmanager: &DataStoreManager,
+    kubemanager: &KubeClientManager,
+    command: &str,
+    args: &HashMap<String, String>,
+) -> Result<(), ProtectionDenied> {
+    if !is_mutating(command) {
+        return Ok(());
+    }
+    let context = args
+        .get(TARGET_CONTEXT_ARG)
+        .filter(|c| !c.is_empty())
+        .cloned()
+        .unwrap_or_else(|| kubemanager.context());
+    let protection = protection_for(dsmanager, &context);
+    let result = check_command(&protection, command, args);
+    if let Err(denied) = &result {
+        info!("Blocked {} in context {}: {}", command, denied.context, denied.message);
+        window.emit(PROTECTION_CHANNEL, denied.clone()).unwrap();
+    }
+    result
+}
@@ -0,0 +1,161 @@
+use regex::RegexBuilder;
+use rusqlite::{OptionalExtension, Result, Row};
+use crate::store::open_connection;
+
+pub(crate) const SQL_CREATE_ALERT_RULES: &str = "\
+    CREATE TABLE IF NOT EXISTS log_alert_rules (\
+        id INTEGER PRIMARY KEY AUTOINCREMENT, \
+        name TEXT NOT NULL, \
+        context TEXT NOT NULL, \
+        namespace TEXT NOT NULL, \
+        selector TEXT, \
+        pattern TEXT NOT NULL, \
+        ignore_case INTEGER NOT NULL, \
+        threshold INTEGER NOT NULL, \
+        window_minutes INTEGER NOT NULL, \
+        enabled INTEGER NOT NULL);";
+
+/// Fires when `pattern` matches more than `threshold` lines of the pods in `ns` within
+/// `window_minutes`.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct AlertRule {
+    /// Zero for a rule that was not saved yet.
+    #[serde(default)]
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    /// Empty means the context current when the rule is saved.
+    #[serde(default)]
+    pub(crate) context: String,
+    pub(crate) ns: String,
+    /// A label selector to watch only some pods of the namespace.
+    #[serde(default)]
+    pub(crate) selector: Option<String>,
+    pub(crate) pattern: String,
+    #[serde(default)]
+    pub(crate) ignore_case: bool,
+    pub(crate) threshold: u32,
+    pub(crate) window_minutes: u32,
+    #[serde(default = "_enabled")]
+    pub(crate) enabled: bool,
+}
+
+fn _enabled() -> bool {
+    true
+}
+
+impl AlertRule {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("An alert rule needs a name".to_string());
+        }
+        if self.ns.trim().is_empty() {
+            return Err(format!("{}: a namespace is required", self.name));
+        }
+        if self.window_minutes == 0 {
+            return Err(format!("{}: the window must be at least a minute", self.name));
+        }
+        RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map(|_| ())
+            .map_err(|e| format!("{}: invalid pattern: {}", self.name, e))
+    }
+}
+
+pub(crate) fn list() -> Result<Vec<AlertRule>> {
+    let connection = open_connection()?;
+    let mut stmt = connection.prepare(
+        "SELECT id, name, context, namespace, selector, pattern, ignore_case, threshold, window_minutes, enabled \
+         FROM log_alert_rules ORDER BY name",
+    )?;
+    let rows = stmt.query_map([], _from_row)?;
+    rows.collect()
+}
+
+pub(crate) fn get(id: i64) -> Result<Option<AlertRule>> {
+    let connection = open_connection()?;
+    connection
+        .query_row(
+            "SELECT id, name, context, namespace, selector, pattern, ignore_case, threshold, window_minutes, enabled \
+             FROM log_alert_rules WHERE id = ?1",
+            [id],
+            _from_row,
+        )
+        .optional()
+}
+
+/// Inserts a new rule or replaces a saved one, and returns it with its id.
+pub(crate) fn save(rule: &AlertRule) -> Result<AlertRule, String> {
+    rule.validate()?;
+    let connection = open_connection().map_err(|e| e.to_string())?;
+    let mut rule = rule.clone();
+    if rule.id != 0 && get(rule.id).map_err(|e| e.to_string())?.is_none() {
+        return Err(format!("Alert rule {} not found", rule.id));
+    }
+    if rule.id == 0 {
+        connection
+            .execute(
+                "INSERT INTO log_alert_rules (name, context, namespace, selector, pattern, ignore_case, threshold, window_minutes, enabled) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                (
+                    &rule.name,
+                    &rule.context,
+                    &rule.ns,
+                    &rule.selector,
+                    &rule.pattern,
+                    rule.ignore_case,
+                    rule.threshold,
+                    rule.window_minutes,
+                    rule.enabled,
+                ),
+            )
+            .map_err(|e| e.to_string())?;
+        rule.id = connection.last_insert_rowid();
+    } else {
+        connection
+            .execute(
+                "UPDATE log_alert_rules SET name = ?1, context = ?2, namespace = ?3, selector = ?4, pattern = ?5, \
+                 ignore_case = ?6, threshold = ?7, window_minutes = ?8, enabled = ?9 WHERE id = ?10",
+                (
+                    &rule.name,
+                    &rule.context,
+                    &rule.ns,
+                    &rule.selector,
+                    &rule.pattern,
+                    rule.ignore_case,
+                    rule.threshold,
+                    rule.window_minutes,
+                    rule.enabled,
+                    rule.id,
+                ),
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(rule)
+}
+
+pub(crate) fn delete(id: i64) -> Result<(), String> {
+    let connection = open_connection().map_err(|e| e.to_string())?;
+    let rows = connection
+        .execute("DELETE FROM log_alert_rules WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+    if rows == 0 {
+        return Err(format!("Alert rule {} not found", id));
+    }
+    Ok(())
+}
+
+fn _from_row(row: &Row) -> Result<AlertRule> {
+    Ok(AlertRule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        context: row.get(2)?,
+        ns: row.get(3)?,
+        selector: row.get(4)?,
+        pattern: row.get(5)?,
+        ignore_case: row.get(6)?,
+        threshold: row.get(7)?,
+        window_minutes: row.get(8)?,
+        enabled: row.get(9)?,
+    })
+}
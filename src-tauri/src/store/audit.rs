@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use rusqlite::{params_from_iter, OptionalExtension, Result, Row};
+use crate::store::open_connection;
+
+const DEFAULT_QUERY_LIMIT: u32 = 200;
+
+pub(crate) const SQL_CREATE_AUDIT_LOG: &str = "\
+    CREATE TABLE IF NOT EXISTS audit_log (\
+        id INTEGER PRIMARY KEY AUTOINCREMENT, \
+        ts INTEGER NOT NULL, \
+        context TEXT NOT NULL, \
+        api_version TEXT NOT NULL, \
+        kind TEXT NOT NULL, \
+        namespace TEXT NOT NULL, \
+        name TEXT NOT NULL, \
+        verb TEXT NOT NULL, \
+        before TEXT, \
+        after TEXT, \
+        success INTEGER NOT NULL, \
+        message TEXT NOT NULL, \
+        undo_of INTEGER);";
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct AuditRecord {
+    pub(crate) id: i64,
+    pub(crate) ts: i64,
+    pub(crate) context: String,
+    pub(crate) api_version: String,
+    pub(crate) kind: String,
+    pub(crate) ns: String,
+    pub(crate) name: String,
+    pub(crate) verb: String,
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+    pub(crate) success: bool,
+    pub(crate) message: String,
+    pub(crate) undo_of: Option<i64>,
+    /// Set when the log is read for the frontend, not stored.
+    #[serde(default)]
+    pub(crate) undoable: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    pub(crate) context: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) ns: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) verb: Option<String>,
+    pub(crate) since: Option<i64>,
+    pub(crate) until: Option<i64>,
+    pub(crate) limit: u32,
+}
+
+impl AuditQuery {
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> Self {
+        let text = |key: &str| args.get(key).cloned().filter(|v| !v.is_empty());
+        AuditQuery {
+            context: text("context"),
+            kind: text("kind"),
+            ns: text("ns"),
+            name: text("name"),
+            verb: text("verb"),
+            since: args.get("since").and_then(|v| v.parse().ok()),
+            until: args.get("until").and_then(|v| v.parse().ok()),
+            limit: args.get("limit").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_QUERY_LIMIT),
+        }
+    }
+}
+
+pub(crate) fn record(entry: &AuditRecord) -> Result<i64> {
+    let connection = open_connection()?;
+    connection.execute(
+        "INSERT INTO audit_log (ts, context, api_version, kind, namespace, name, verb, before, after, success, message, undo_of) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        (
+            &entry.ts,
+            &entry.context,
+            &entry.api_version,
+            &entry.kind,
+            &entry.ns,
+            &entry.name,
+            &entry.verb,
+            &entry.before,
+            &entry.after,
+            &entry.success,
+            &entry.message,
+            &entry.undo_of,
+        ),
+    )?;
+    Ok(connection.last_insert_rowid())
+}
+
+pub(crate) fn query(q: &AuditQuery) -> Result<Vec<AuditRecord>> {
+    let connection = open_connection()?;
+    let mut clauses: Vec<&str> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+    let filters = [
+        ("context = ?", &q.context),
+        ("kind = ? COLLATE NOCASE", &q.kind),
+        ("namespace = ?", &q.ns),
+        ("name LIKE '%' || ? || '%'", &q.name),
+        ("verb = ?", &q.verb),
+    ];
+    for (clause, value) in filters.iter() {
+        if let Some(value) = value {
+            clauses.push(*clause);
+            values.push(value.clone());
+        }
+    }
+    if let Some(since) = q.since {
+        clauses.push("ts >= CAST(? AS INTEGER)");
+        values.push(since.to_string());
+    }
+    if let Some(until) = q.until {
+        clauses.push("ts <= CAST(? AS INTEGER)");
+        values.push(until.to_string());
+    }
+    let mut sql = "SELECT id, ts, context, api_version, kind, namespace, name, verb, before, after, success, message, undo_of \
+                   FROM audit_log".to_string();
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(&format!(" ORDER BY id DESC LIMIT {}", q.limit));
+    let mut stmt = connection.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values.iter()), _from_row)?;
+    rows.collect()
+}
+
+pub(crate) fn get(id: i64) -> Result<Option<AuditRecord>> {
+    let connection = open_connection()?;
+    connection
+        .query_row(
+            "SELECT id, ts, context, api_version, kind, namespace, name, verb, before, after, success, message, undo_of \
+             FROM audit_log WHERE id = ?1",
+            [id],
+            _from_row,
+        )
+        .optional()
+}
+
+/// Returns the id of the successful undo of a record, if it has been undone already.
+pub(crate) fn undone_by(id: i64) -> Result<Option<i64>> {
+    let connection = open_connection()?;
+    connection
+        .query_row(
+            "SELECT id FROM audit_log WHERE undo_of = ?1 AND success = 1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+fn _from_row(row: &Row) -> Result<AuditRecord> {
+    Ok(AuditRecord {
+        id: row.get(0)?,
+        ts: row.get(1)?,
+        context: row.get(2)?,
+        api_version: row.get(3)?,
+        kind: row.get(4)?,
+        ns: row.get(5)?,
+        name: row.get(6)?,
+        verb: row.get(7)?,
+        before: row.get(8)?,
+        after: row.get(9)?,
+        success: row.get(10)?,
+        message: row.get(11)?,
+        undo_of: row.get(12)?,
+        undoable: false,
+    })
+}
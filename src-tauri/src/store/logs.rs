@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::DateTime;
+use rusqlite::{params_from_iter, Connection, Result, Row};
+use crate::store::{preference, PKEY_LOG_CAPTURE, PKEY_LOG_RETENTION_DAYS, PKEY_LOG_RETENTION_MB};
+
+const LOG_STORE_FILE: &str = "yaki_logs";
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+const DEFAULT_RETENTION_MB: i64 = 512;
+const DEFAULT_SEARCH_LIMIT: u32 = 500;
+/// How often a running capture applies the retention limits.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(600);
+/// Oldest rows removed per round while the store is over its size limit.
+const PRUNE_BATCH: i64 = 10000;
+
+/// Captured lines live in their own file next to the preferences, so a large history does not
+/// slow down or bloat the main data file. `auto_vacuum` only takes effect on a new file.
+const SQL_CREATE_LOG_STORE: &str = "\
+    PRAGMA auto_vacuum = INCREMENTAL;
+    CREATE TABLE IF NOT EXISTS log_lines (\
+        id INTEGER PRIMARY KEY AUTOINCREMENT, \
+        ts INTEGER NOT NULL, \
+        context TEXT NOT NULL, \
+        namespace TEXT NOT NULL, \
+        pod TEXT NOT NULL, \
+        container TEXT NOT NULL, \
+        line TEXT NOT NULL);
+    CREATE INDEX IF NOT EXISTS log_lines_ts ON log_lines (ts);
+    CREATE INDEX IF NOT EXISTS log_lines_pod ON log_lines (namespace, pod);
+    CREATE VIRTUAL TABLE IF NOT EXISTS log_lines_fts USING fts5(line, content='log_lines', content_rowid='id');
+    CREATE TRIGGER IF NOT EXISTS log_lines_ai AFTER INSERT ON log_lines BEGIN \
+        INSERT INTO log_lines_fts (rowid, line) VALUES (new.id, new.line); \
+    END;
+    CREATE TRIGGER IF NOT EXISTS log_lines_ad AFTER DELETE ON log_lines BEGIN \
+        INSERT INTO log_lines_fts (log_lines_fts, rowid, line) VALUES ('delete', old.id, old.line); \
+    END;";
+
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct StoredLogLine {
+    pub(crate) id: i64,
+    /// Milliseconds since the epoch, from the line's timestamp when the tail asked for them.
+    pub(crate) ts: i64,
+    pub(crate) context: String,
+    pub(crate) ns: String,
+    pub(crate) pod: String,
+    pub(crate) container: String,
+    pub(crate) line: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LogSearchQuery {
+    /// Words that must all appear in the line.
+    pub(crate) text: Option<String>,
+    pub(crate) context: Option<String>,
+    pub(crate) ns: Option<String>,
+    pub(crate) pod: Option<String>,
+    pub(crate) container: Option<String>,
+    pub(crate) since: Option<i64>,
+    pub(crate) until: Option<i64>,
+    pub(crate) limit: u32,
+}
+
+impl LogSearchQuery {
+    /// `since` and `until` are milliseconds since the epoch or RFC 3339 times.
+    pub(crate) fn from_args(args: &HashMap<String, String>) -> std::result::Result<Self, String> {
+        let text = |key: &str| args.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let time = |key: &str| -> std::result::Result<Option<i64>, String> {
+            match text(key) {
+                Some(value) => match value.parse::<i64>() {
+                    Ok(ms) => Ok(Some(ms)),
+                    Err(_) => DateTime::parse_from_rfc3339(&value)
+                        .map(|t| Some(t.timestamp_millis()))
+                        .map_err(|e| format!("Invalid {} {}: {}", key, value, e)),
+                },
+                None => Ok(None),
+            }
+        };
+        Ok(LogSearchQuery {
+            text: text("text"),
+            context: text("context"),
+            ns: text("ns"),
+            pod: text("pod"),
+            container: text("container"),
+            since: time("since")?,
+            until: time("until")?,
+            limit: args.get("limit").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_SEARCH_LIMIT),
+        })
+    }
+}
+
+fn open() -> Result<Connection> {
+    let mut path: PathBuf = dirs::home_dir().unwrap();
+    path.push(".nirops");
+    path.push(LOG_STORE_FILE);
+    let connection = Connection::open(path)?;
+    // Several tails write at once, each through its own connection
+    connection.busy_timeout(Duration::from_secs(5))?;
+    connection.execute_batch(SQL_CREATE_LOG_STORE)?;
+    Ok(connection)
+}
+
+/// Returns the newest matching lines, oldest first.
+pub(crate) fn search(q: &LogSearchQuery) -> Result<Vec<StoredLogLine>> {
+    let connection = open()?;
+    let mut sql = "SELECT log_lines.id, ts, context, namespace, pod, container, log_lines.line FROM log_lines".to_string();
+    let mut clauses: Vec<&str> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+    if let Some(text) = &q.text {
+        sql.push_str(" JOIN log_lines_fts ON log_lines_fts.rowid = log_lines.id");
+        clauses.push("log_lines_fts MATCH ?");
+        values.push(_match_expression(text));
+    }
+    let filters = [
+        ("context = ?", &q.context),
+        ("namespace = ?", &q.ns),
+        ("pod LIKE '%' || ? || '%'", &q.pod),
+        ("container = ?", &q.container),
+    ];
+    for (clause, value) in filters.iter() {
+        if let Some(value) = value {
+            clauses.push(*clause);
+            values.push(value.clone());
+        }
+    }
+    if let Some(since) = q.since {
+        clauses.push("ts >= CAST(? AS INTEGER)");
+        values.push(since.to_string());
+    }
+    if let Some(until) = q.until {
+        clauses.push("ts <= CAST(? AS INTEGER)");
+        values.push(until.to_string());
+    }
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(&format!(" ORDER BY ts DESC, log_lines.id DESC LIMIT {}", q.limit));
+    let mut stmt = connection.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values.iter()), _from_row)?;
+    let mut lines = rows.collect::<Result<Vec<StoredLogLine>>>()?;
+    lines.reverse();
+    Ok(lines)
+}
+
+/// Quotes every word, so that text like `error: timeout` is searched for as is instead of being
+/// read as FTS5 query syntax.
+fn _match_expression(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn _from_row(row: &Row) -> Result<StoredLogLine> {
+    Ok(StoredLogLine {
+        id: row.get(0)?,
+        ts: row.get(1)?,
+        context: row.get(2)?,
+        ns: row.get(3)?,
+        pod: row.get(4)?,
+        container: row.get(5)?,
+        line: row.get(6)?,
+    })
+}
+
+struct CapturedLine {
+    ts: i64,
+    pod: String,
+    container: String,
+    line: String,
+}
+
+/// Writes the lines of one tail to the log store. Lines are queued and written in one
+/// transaction when the tail flushes.
+pub struct LogCapture {
+    connection: Connection,
+    context: String,
+    ns: String,
+    pending: Vec<CapturedLine>,
+    max_age_days: i64,
+    max_bytes: i64,
+    last_pruned: Instant,
+}
+
+impl LogCapture {
+    /// Returns None unless capture is turned on in the preferences and the store can be opened.
+    pub(crate) fn open(context: &str, ns: &str) -> Option<Self> {
+        if preference(PKEY_LOG_CAPTURE).as_deref() != Some("true") {
+            return None;
+        }
+        let limit = |key: &str, default: i64| preference(key).and_then(|v| v.parse().ok()).unwrap_or(default);
+        let connection = match open() {
+            Ok(connection) => connection,
+            Err(err) => {
+                warn!("Log capture is off, the log store could not be opened: {}", err);
+                return None;
+            }
+        };
+        let mut capture = LogCapture {
+            connection,
+            context: context.to_string(),
+            ns: ns.to_string(),
+            pending: Vec::new(),
+            max_age_days: limit(PKEY_LOG_RETENTION_DAYS, DEFAULT_RETENTION_DAYS),
+            max_bytes: limit(PKEY_LOG_RETENTION_MB, DEFAULT_RETENTION_MB) * 1024 * 1024,
+            last_pruned: Instant::now(),
+        };
+        capture.prune();
+        Some(capture)
+    }
+
+    /// Queues a line under the time the server logged it, or now for a line that came without one.
+    pub(crate) fn push(&mut self, pod: &str, container: &str, line: &str, ts: Option<i64>) {
+        let ts = ts.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64);
+        self.pending.push(CapturedLine {
+            ts,
+            pod: pod.to_string(),
+            container: container.to_string(),
+            line: line.to_string(),
+        });
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            if let Err(err) = self.write() {
+                warn!("Dropped {} captured log lines: {}", self.pending.len(), err);
+            }
+            self.pending.clear();
+        }
+        if self.last_pruned.elapsed() >= RETENTION_INTERVAL {
+            self.prune();
+        }
+    }
+
+    fn write(&mut self) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut stmt = transaction.prepare_cached(
+                "INSERT INTO log_lines (ts, context, namespace, pod, container, line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for line in &self.pending {
+                stmt.execute((&line.ts, &self.context, &self.ns, &line.pod, &line.container, &line.line))?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Removes lines older than the age limit, then the oldest lines until the store fits in
+    /// its size limit.
+    fn prune(&mut self) {
+        self.last_pruned = Instant::now();
+        if let Err(err) = self._prune() {
+            warn!("Failed to apply log retention: {}", err);
+        }
+    }
+
+    fn _prune(&self) -> Result<()> {
+        let cutoff = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64
+            - self.max_age_days * 24 * 60 * 60 * 1000;
+        let mut removed = self.connection.execute("DELETE FROM log_lines WHERE ts < ?1", [cutoff])?;
+        while self.used_bytes()? > self.max_bytes {
+            let rows = self.connection.execute(
+                "DELETE FROM log_lines WHERE id IN (SELECT id FROM log_lines ORDER BY id LIMIT ?1)",
+                [PRUNE_BATCH],
+            )?;
+            if rows == 0 {
+                break;
+            }
+            removed += rows;
+        }
+        if removed > 0 {
+            debug!("Removed {} captured log lines", removed);
+            self.connection.execute_batch("PRAGMA incremental_vacuum;")?;
+        }
+        Ok(())
+    }
+
+    /// Pages in use, leaving out free pages that new lines will reuse.
+    fn used_bytes(&self) -> Result<i64> {
+        let pragma = |name: &str| -> Result<i64> {
+            self.connection.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+        };
+        Ok((pragma("page_count")? - pragma("freelist_count")?) * pragma("page_size")?)
+    }
+}
@@ -5,12 +5,20 @@ use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
+pub mod alerts;
+pub mod audit;
+pub mod logs;
+pub mod templates;
+
 pub const LICENSE_PUBLIC_KEY: &str = "LICENSE_PUBLIC_KEY";
 pub const LICENSE_STRING_KEY: &str = "LICENSE_STRING_KEY";
 pub const CUSTOM_NS_LIST: &str = "CUSTOM_NS_LIST";
 pub const PKEY_KUBECONFIG_FILE_LOCATION: &str = "PKEY_KUBECONFIG_FILE_LOCATION";
 pub const PKEY_PROXY_URL: &str = "PKEY_PROXY_URL";
 pub const KEY_EULA_ACCEPT: &str = "KEY_EULA_ACCEPT";
+pub const PKEY_LOG_CAPTURE: &str = "PKEY_LOG_CAPTURE";
+pub const PKEY_LOG_RETENTION_DAYS: &str = "PKEY_LOG_RETENTION_DAYS";
+pub const PKEY_LOG_RETENTION_MB: &str = "PKEY_LOG_RETENTION_MB";
 
 pub const LICENSE_PUBLIC_KEY_VALUE: &str = "rsa_string";
 
@@ -203,6 +211,20 @@ fn create_file_if_absent() {
     }
 }
 
+/// Opens a separate connection to the data file. Template lookups come from worker threads,
+/// so they cannot go through the `DataStoreManager` held by the app state.
+pub(crate) fn open_connection() -> Result<Connection> {
+    Connection::open(Path::new(&get_file_name()))
+}
+
+/// Reads a preference from a worker thread.
+pub(crate) fn preference(key: &str) -> Option<String> {
+    let connection = open_connection().ok()?;
+    connection
+        .query_row("SELECT value FROM preferences WHERE key = ?1", [key], |row| row.get(0))
+        .ok()
+}
+
 fn get_file_name() -> String {
     let mut file_path: PathBuf = dirs::home_dir().unwrap();
     const OS: &str = env::consts::OS;
@@ -225,6 +247,9 @@ fn _intialize() -> Result<DataStoreManager, Box<dyn Error>> {
     const SQL_INIT_STATEMENTS: &str = "\
     CREATE TABLE IF NOT EXISTS preferences (key TEXT, value TEXT);";
     sm.connection.execute(SQL_INIT_STATEMENTS, ()).unwrap();
+    sm.connection.execute(audit::SQL_CREATE_AUDIT_LOG, ()).unwrap();
+    sm.connection.execute(alerts::SQL_CREATE_ALERT_RULES, ()).unwrap();
+    templates::seed(&sm.connection)?;
     sm.upsert(Preference {
         key: LICENSE_PUBLIC_KEY.parse().unwrap(),
         value: LICENSE_PUBLIC_KEY_VALUE.parse().unwrap(),
@@ -0,0 +1,519 @@
+use std::collections::{BTreeSet, HashMap};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use crate::store::open_connection;
+
+static VARIABLE_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap());
+const NAME_RULE: &str = "[a-z0-9]([-a-z0-9]*[a-z0-9])?";
+
+pub(crate) const SQL_CREATE_TEMPLATES: &str = "\
+    CREATE TABLE IF NOT EXISTS templates (\
+        name TEXT PRIMARY KEY, \
+        kind TEXT NOT NULL, \
+        description TEXT NOT NULL, \
+        body TEXT NOT NULL, \
+        variables TEXT NOT NULL, \
+        builtin INTEGER NOT NULL);";
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    String,
+    Integer,
+    Boolean,
+    Enum,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct TemplateVariable {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) kind: VariableType,
+    #[serde(default)]
+    pub(crate) default: Option<String>,
+    #[serde(default)]
+    pub(crate) required: bool,
+    /// A regex the whole value must match, for string variables.
+    #[serde(default)]
+    pub(crate) pattern: Option<String>,
+    /// The allowed values, for enum variables.
+    #[serde(default)]
+    pub(crate) options: Vec<String>,
+    #[serde(default)]
+    pub(crate) description: String,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Template {
+    pub(crate) name: String,
+    pub(crate) kind: String,
+    #[serde(default)]
+    pub(crate) description: String,
+    pub(crate) body: String,
+    #[serde(default)]
+    pub(crate) variables: Vec<TemplateVariable>,
+    #[serde(default)]
+    pub(crate) builtin: bool,
+}
+
+impl TemplateVariable {
+    fn new(name: &str, kind: VariableType, default: Option<&str>, description: &str) -> Self {
+        TemplateVariable {
+            name: name.to_string(),
+            kind,
+            default: default.map(|d| d.to_string()),
+            required: true,
+            pattern: None,
+            options: Vec::new(),
+            description: description.to_string(),
+        }
+    }
+
+    fn resource_name() -> Self {
+        let mut variable = TemplateVariable::new("name", VariableType::String, None, "Name of the resource");
+        variable.pattern = Some(NAME_RULE.to_string());
+        variable
+    }
+
+    fn namespace() -> Self {
+        let mut variable = TemplateVariable::new("namespace", VariableType::String, Some("default"), "Namespace");
+        variable.pattern = Some(NAME_RULE.to_string());
+        variable
+    }
+
+    fn one_of(name: &str, options: &[&str], description: &str) -> Self {
+        let mut variable = TemplateVariable::new(name, VariableType::Enum, options.first().copied(), description);
+        variable.options = options.iter().map(|o| o.to_string()).collect();
+        variable
+    }
+
+    /// The value as it is written when the placeholder is a whole YAML scalar. Strings and enum
+    /// options go through the YAML emitter so a value like `on`, `080` or `a: b` stays a string;
+    /// numbers and booleans have already been validated and are written as they are.
+    fn scalar(&self, value: &str) -> String {
+        match self.kind {
+            VariableType::Integer | VariableType::Boolean => value.to_string(),
+            VariableType::String | VariableType::Enum => {
+                let emitted = serde_yaml::to_string(value).unwrap_or_default();
+                let emitted = emitted.strip_prefix("---\n").unwrap_or(&emitted);
+                emitted.trim_end().to_string()
+            }
+        }
+    }
+
+    /// A value that passes validation, used where a required variable has no default.
+    fn placeholder(&self) -> String {
+        match self.kind {
+            VariableType::Integer => "0".to_string(),
+            VariableType::Boolean => "false".to_string(),
+            VariableType::Enum => self.options.first().cloned().unwrap_or_default(),
+            VariableType::String => "changeme".to_string(),
+        }
+    }
+
+    fn validate(&self, value: &str) -> Result<(), String> {
+        match self.kind {
+            VariableType::Integer => {
+                value.parse::<i64>().map_err(|_| format!("{} must be a whole number", self.name))?;
+            },
+            VariableType::Boolean => {
+                if value != "true" && value != "false" {
+                    return Err(format!("{} must be true or false", self.name));
+                }
+            },
+            VariableType::Enum => {
+                if !self.options.iter().any(|o| o == value) {
+                    return Err(format!("{} must be one of {}", self.name, self.options.join(", ")));
+                }
+            },
+            VariableType::String => {
+                if value.contains('\n') {
+                    return Err(format!("{} must be a single line", self.name));
+                }
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            let re = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| format!("{}: invalid pattern: {}", self.name, e))?;
+            if !re.is_match(value) {
+                return Err(format!("{} must match {}", self.name, pattern));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Template {
+    /// Checks that every `{{variable}}` in the body is declared and that declarations are usable.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Template name is required".to_string());
+        }
+        let declared: BTreeSet<&str> = self.variables.iter().map(|v| v.name.as_str()).collect();
+        if declared.len() != self.variables.len() {
+            return Err("Variable names must be unique".to_string());
+        }
+        let undeclared: Vec<String> = used_variables(&self.body)
+            .into_iter()
+            .filter(|v| !declared.contains(v.as_str()))
+            .collect();
+        if !undeclared.is_empty() {
+            return Err(format!("Undeclared variables: {}", undeclared.join(", ")));
+        }
+        for variable in &self.variables {
+            if variable.kind == VariableType::Enum && variable.options.is_empty() {
+                return Err(format!("{} needs at least one option", variable.name));
+            }
+            if let Some(default) = &variable.default {
+                variable.validate(default).map_err(|e| format!("Default of {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Substitutes the variables and checks the result parses as YAML with apiVersion and kind
+    /// on every document. All validation errors are reported together.
+    pub(crate) fn render(&self, values: &HashMap<String, String>) -> Result<String, String> {
+        let mut resolved: HashMap<&str, (&TemplateVariable, String)> = HashMap::new();
+        let mut errors: Vec<String> = Vec::new();
+        for variable in &self.variables {
+            let value = values
+                .get(&variable.name)
+                .filter(|v| !v.trim().is_empty())
+                .or(variable.default.as_ref());
+            match value {
+                Some(value) => match variable.validate(value.trim()) {
+                    Ok(()) => {
+                        resolved.insert(&variable.name, (variable, value.trim().to_string()));
+                    },
+                    Err(err) => errors.push(err),
+                },
+                None if variable.required => errors.push(format!("{} is required", variable.name)),
+                None => {
+                    resolved.insert(&variable.name, (variable, String::new()));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        // A placeholder inside a longer scalar, like `repo/app:{{tag}}`, takes the validated text
+        // as it is, since quoting it would put the quotes in the middle of the value
+        let rendered = VARIABLE_PATTERN
+            .replace_all(&self.body, |caps: &Captures| {
+                let placeholder = caps.get(0).unwrap();
+                match resolved.get(&caps[1]) {
+                    Some((variable, value)) if _is_whole_scalar(&self.body, placeholder.start(), placeholder.end()) => {
+                        variable.scalar(value)
+                    },
+                    Some((_, value)) => value.clone(),
+                    None => String::new(),
+                }
+            })
+            .to_string();
+        _check_manifests(&rendered)?;
+        Ok(rendered)
+    }
+
+    /// Renders with the defaults and a placeholder for anything that has none, giving a manifest
+    /// the user can edit before applying.
+    pub(crate) fn sample(&self) -> Result<String, String> {
+        let values: HashMap<String, String> = self
+            .variables
+            .iter()
+            .filter(|v| v.default.as_ref().map(|d| d.trim().is_empty()).unwrap_or(true))
+            .map(|v| (v.name.clone(), v.placeholder()))
+            .collect();
+        self.render(&values)
+    }
+}
+
+fn used_variables(body: &str) -> BTreeSet<String> {
+    VARIABLE_PATTERN
+        .captures_iter(body)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+/// Whether the placeholder between `start` and `end` is a whole block style scalar: a mapping
+/// key, a mapping value or a sequence item on its own.
+fn _is_whole_scalar(body: &str, start: usize, end: usize) -> bool {
+    let line_start = body[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = body[end..].find('\n').map(|i| end + i).unwrap_or(body.len());
+    let before = &body[line_start..start];
+    let after = body[end..line_end].trim_end();
+    let starts_value = before.trim().is_empty()
+        || (before.ends_with([' ', '\t'])
+            && (before.trim_end().ends_with(':') || before.trim_end().ends_with('-')));
+    let ends_value = after.is_empty()
+        || after == ":"
+        || after.starts_with(": ")
+        || (after.trim_start().starts_with('#') && after.starts_with([' ', '\t']));
+    starts_value && ends_value
+}
+
+fn _check_manifests(rendered: &str) -> Result<(), String> {
+    use serde::Deserialize;
+    for document in serde_yaml::Deserializer::from_str(rendered) {
+        let value = serde_yaml::Value::deserialize(document).map_err(|e| format!("Rendered YAML is invalid: {}", e))?;
+        if value.is_null() {
+            continue;
+        }
+        if value.get("apiVersion").is_none() || value.get("kind").is_none() {
+            return Err("Every rendered document needs an apiVersion and a kind".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn builtin_templates() -> Vec<Template> {
+    let image = |default: &str| TemplateVariable::new("image", VariableType::String, Some(default), "Container image");
+    let replicas = TemplateVariable::new("replicas", VariableType::Integer, Some("1"), "Number of replicas");
+    let key = TemplateVariable::new("key", VariableType::String, Some("key"), "Data key");
+    let value = TemplateVariable::new("value", VariableType::String, Some(""), "Data value");
+    vec![
+        _builtin("Namespace", include_str!("../kube/yaml/ns.yaml"), vec![TemplateVariable::resource_name()]),
+        _builtin(
+            "ConfigMap",
+            include_str!("../kube/yaml/configmap.yaml"),
+            vec![TemplateVariable::resource_name(), TemplateVariable::namespace(), key.clone(), value.clone()],
+        ),
+        _builtin(
+            "Secret",
+            include_str!("../kube/yaml/secret.yaml"),
+            vec![TemplateVariable::resource_name(), TemplateVariable::namespace(), key, value],
+        ),
+        _builtin(
+            "Deployment",
+            include_str!("../kube/yaml/deployment.yaml"),
+            vec![
+                TemplateVariable::resource_name(),
+                TemplateVariable::namespace(),
+                image("nginx:stable"),
+                replicas.clone(),
+                TemplateVariable::new("port", VariableType::Integer, Some("80"), "Container port"),
+            ],
+        ),
+        _builtin(
+            "Service",
+            include_str!("../kube/yaml/service.yaml"),
+            vec![
+                TemplateVariable::resource_name(),
+                TemplateVariable::namespace(),
+                TemplateVariable::one_of("type", &["ClusterIP", "NodePort", "LoadBalancer"], "Service type"),
+                TemplateVariable::new("app", VariableType::String, None, "Value of the app label to select pods"),
+                TemplateVariable::new("port", VariableType::Integer, Some("80"), "Service port"),
+                TemplateVariable::new("target_port", VariableType::Integer, Some("80"), "Container port"),
+            ],
+        ),
+        _builtin(
+            "Pod",
+            include_str!("../kube/yaml/pod.yaml"),
+            vec![
+                TemplateVariable::resource_name(),
+                TemplateVariable::namespace(),
+                image("busybox:stable"),
+                TemplateVariable::one_of("restart_policy", &["Always", "OnFailure", "Never"], "Restart policy"),
+            ],
+        ),
+        _builtin(
+            "ReplicaSet",
+            include_str!("../kube/yaml/replicaset.yaml"),
+            vec![TemplateVariable::resource_name(), TemplateVariable::namespace(), image("nginx:stable"), replicas],
+        ),
+        _builtin(
+            "Job",
+            include_str!("../kube/yaml/job.yaml"),
+            vec![
+                TemplateVariable::resource_name(),
+                TemplateVariable::namespace(),
+                image("busybox:stable"),
+                TemplateVariable::new("backoff_limit", VariableType::Integer, Some("6"), "Retries before the job fails"),
+            ],
+        ),
+        _builtin(
+            "CronJob",
+            include_str!("../kube/yaml/cronjob.yaml"),
+            vec![
+                TemplateVariable::resource_name(),
+                TemplateVariable::namespace(),
+                image("busybox:stable"),
+                TemplateVariable::new("schedule", VariableType::String, Some("*/5 * * * *"), "Cron schedule"),
+                TemplateVariable::new("suspend", VariableType::Boolean, Some("false"), "Create the cron job suspended"),
+            ],
+        ),
+    ]
+}
+
+fn _builtin(kind: &str, body: &str, variables: Vec<TemplateVariable>) -> Template {
+    Template {
+        name: kind.to_string(),
+        kind: kind.to_string(),
+        description: format!("Basic {}", kind),
+        body: body.to_string(),
+        variables,
+        builtin: true,
+    }
+}
+
+/// Creates the table and refreshes the built-ins, so fixes to them reach existing installs.
+/// User templates are left untouched.
+pub(crate) fn seed(connection: &Connection) -> Result<()> {
+    connection.execute(SQL_CREATE_TEMPLATES, ())?;
+    for template in builtin_templates() {
+        _upsert(connection, &template)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn list() -> Result<Vec<Template>> {
+    let connection = open_connection()?;
+    let mut stmt = connection.prepare(
+        "SELECT name, kind, description, body, variables, builtin FROM templates ORDER BY builtin DESC, name",
+    )?;
+    let rows = stmt.query_map([], _from_row)?;
+    rows.collect()
+}
+
+pub(crate) fn get(name: &str) -> Result<Option<Template>> {
+    let connection = open_connection()?;
+    connection
+        .query_row(
+            "SELECT name, kind, description, body, variables, builtin FROM templates WHERE name = ?1",
+            [name],
+            _from_row,
+        )
+        .optional()
+}
+
+/// The built-in template for a kind, matched case-insensitively.
+pub(crate) fn for_kind(kind: &str) -> Result<Option<Template>> {
+    let connection = open_connection()?;
+    connection
+        .query_row(
+            "SELECT name, kind, description, body, variables, builtin FROM templates \
+             WHERE kind = ?1 COLLATE NOCASE AND builtin = 1",
+            [kind],
+            _from_row,
+        )
+        .optional()
+}
+
+pub(crate) fn save(template: &Template) -> Result<(), String> {
+    template.validate()?;
+    if let Some(existing) = get(&template.name).map_err(|e| e.to_string())? {
+        if existing.builtin {
+            return Err(format!("{} is a built-in template. Save it under a new name", template.name));
+        }
+    }
+    let connection = open_connection().map_err(|e| e.to_string())?;
+    let mut template = template.clone();
+    template.builtin = false;
+    _upsert(&connection, &template).map(|_| ()).map_err(|e| e.to_string())
+}
+
+pub(crate) fn delete(name: &str) -> Result<(), String> {
+    match get(name).map_err(|e| e.to_string())? {
+        Some(template) if template.builtin => Err(format!("{} is a built-in template and cannot be deleted", name)),
+        Some(_) => {
+            let connection = open_connection().map_err(|e| e.to_string())?;
+            connection
+                .execute("DELETE FROM templates WHERE name = ?1", [name])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        },
+        None => Err(format!("Template {} not found", name)),
+    }
+}
+
+fn _upsert(connection: &Connection, template: &Template) -> Result<usize> {
+    connection.execute(
+        "INSERT OR REPLACE INTO templates (name, kind, description, body, variables, builtin) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &template.name,
+            &template.kind,
+            &template.description,
+            &template.body,
+            serde_json::to_string(&template.variables).unwrap(),
+            template.builtin,
+        ),
+    )
+}
+
+fn _from_row(row: &Row) -> Result<Template> {
+    let variables: String = row.get(4)?;
+    Ok(Template {
+        name: row.get(0)?,
+        kind: row.get(1)?,
+        description: row.get(2)?,
+        body: row.get(3)?,
+        variables: serde_json::from_str(&variables).unwrap_or_default(),
+        builtin: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(body: &str, variables: Vec<TemplateVariable>) -> Template {
+        Template {
+            name: "test".to_string(),
+            kind: "Pod".to_string(),
+            description: String::new(),
+            body: body.to_string(),
+            variables,
+            builtin: false,
+        }
+    }
+
+    fn render(body: &str, variable: TemplateVariable, value: &str) -> Result<String, String> {
+        let header = "apiVersion: v1\nkind: ConfigMap\n";
+        let values: HashMap<String, String> = [(variable.name.clone(), value.to_string())].into_iter().collect();
+        template(&format!("{}{}", header, body), vec![variable])
+            .render(&values)
+            .map(|rendered| rendered.trim_start_matches(header).to_string())
+    }
+
+    #[test]
+    fn quotes_placeholders_that_are_the_whole_value() {
+        let string = || TemplateVariable::new("v", VariableType::String, None, "");
+        let cases = [
+            ("data:\n  key: {{v}}", string(), "1.0", "data:\n  key: \"1.0\""),
+            ("data:\n  key: {{v}}", string(), "on", "data:\n  key: \"on\""),
+            ("data:\n  key: {{ v }}  # comment", string(), "080", "data:\n  key: \"080\"  # comment"),
+            ("data:\n  {{v}}: x", string(), "yes", "data:\n  \"yes\": x"),
+            ("args:\n  - {{v}}", string(), "a: b", "args:\n  - \"a: b\""),
+            ("data:\n  key: {{v}}", string(), "plain", "data:\n  key: plain"),
+            ("replicas: {{v}}", TemplateVariable::new("v", VariableType::Integer, None, ""), "3", "replicas: 3"),
+            ("suspend: {{v}}", TemplateVariable::new("v", VariableType::Boolean, None, ""), "true", "suspend: true"),
+        ];
+        for (body, variable, value, expected) in cases {
+            assert_eq!(render(body, variable, value).as_deref(), Ok(expected), "{} / {}", body, value);
+        }
+    }
+
+    #[test]
+    fn splices_placeholders_inside_a_longer_value() {
+        let string = || TemplateVariable::new("v", VariableType::String, None, "");
+        let cases = [
+            ("image: repo/app:{{v}}", "1.0", "image: repo/app:1.0"),
+            ("image: {{v}}/app:stable", "registry:5000", "image: registry:5000/app:stable"),
+            ("url: http://{{v}}.svc", "api", "url: http://api.svc"),
+            ("name: \"{{v}}-web\"", "on", "name: \"on-web\""),
+            ("args:\n  - --level={{v}}", "1.0", "args:\n  - --level=1.0"),
+        ];
+        for (body, value, expected) in cases.iter() {
+            assert_eq!(render(body, string(), value).as_deref(), Ok(*expected), "{} / {}", body, value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values_before_rendering() {
+        let integer = TemplateVariable::new("v", VariableType::Integer, None, "");
+        assert_eq!(render("replicas: {{v}}", integer, "two"), Err("v must be a whole number".to_string()));
+        let string = TemplateVariable::new("v", VariableType::String, None, "");
+        assert_eq!(render("key: {{v}}", string, ""), Err("v is required".to_string()));
+    }
+}
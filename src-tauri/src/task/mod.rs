@@ -3,7 +3,9 @@ use std::sync::mpsc::Sender;
 pub struct TaskManager {
     m_streamtasklist: Vec<Sender<String>>,
     l_streamtasklist: Vec<Sender<String>>,
-    s_streamtasklist: Vec<Sender<String>>
+    s_streamtasklist: Vec<Sender<String>>,
+    /// Background tails of log alert rules, by rule id. They are not stopped with the log view.
+    a_streamtasklist: Vec<(i64, Sender<String>)>
 }
 
 pub fn intialize() -> TaskManager {
@@ -13,7 +15,8 @@ pub fn intialize() -> TaskManager {
     let tm = TaskManager {
         m_streamtasklist: mtasklist,
         l_streamtasklist: ltasklist,
-        s_streamtasklist: stasklist
+        s_streamtasklist: stasklist,
+        a_streamtasklist: Vec::new()
     };
     tm
 }
@@ -36,6 +39,25 @@ impl TaskManager {
         self.l_streamtasklist.push(val);
     }
 
+    pub fn send_to_logs(&self, val: &str) {
+        for stream in &self.l_streamtasklist {
+            let _ = stream.send(val.to_string());
+        }
+    }
+
+    /// Replaces the running tail of the rule, if any.
+    pub fn add_alert_stream(&mut self, rule: i64, val: Sender<String>) {
+        self.stop_alert_stream(rule);
+        self.a_streamtasklist.push((rule, val));
+    }
+
+    pub fn stop_alert_stream(&mut self, rule: i64) {
+        for (_, tx) in self.a_streamtasklist.iter().filter(|(id, _)| *id == rule) {
+            let _ = tx.send("STOP".to_string());
+        }
+        self.a_streamtasklist.retain(|(id, _)| *id != rule);
+    }
+
     pub fn stopallmstream(&mut self) {
         for tx in &self.m_streamtasklist {
             let _ = tx.send("STOP".to_string());
@@ -28,6 +28,10 @@ export class TauriAdapter {
     get_deployments: 'get_deployments',
     create_resource: 'apply_resource',
     delete_resource: 'delete_resource',
+    preview_delete: 'preview_delete',
+    validate_resource: 'validate_resource',
+    backup_namespace: 'backup_namespace',
+    clone_resources: 'clone_resources',
     get_resource: 'get_resource',
     get_resource_with_metrics: 'get_resource_with_metrics',
     get_pods_for_deployment_async: 'get_pods_for_deployment_async',
@@ -37,8 +41,46 @@ export class TauriAdapter {
     set_current_cluster_context: 'set_current_cluster_context',
     get_current_cluster_context: 'get_current_cluster_context',
     restart_deployments: 'restart_deployments',
+    get_rollout_history: 'get_rollout_history',
+    rollback_rollout: 'rollback_rollout',
+    rollout_status: 'rollout_status',
+    scale_workload: 'scale_workload',
+    pause_rollout: 'pause_rollout',
+    resume_rollout: 'resume_rollout',
+    cordon_node: 'cordon_node',
+    uncordon_node: 'uncordon_node',
+    drain_node: 'drain_node',
+    set_image: 'set_image',
+    get_audit_log: 'get_audit_log',
+    undo_audit_record: 'undo_audit_record',
+    get_context_protection: 'get_context_protection',
+    set_context_protection: 'set_context_protection',
+    set_protection_pattern: 'set_protection_pattern',
+    list_templates: 'list_templates',
+    save_template: 'save_template',
+    delete_template: 'delete_template',
+    render_template: 'render_template',
+    trigger_cron_job: 'trigger_cron_job',
+    suspend_cron_job: 'suspend_cron_job',
+    resume_cron_job: 'resume_cron_job',
+    get_cron_job_schedules: 'get_cron_job_schedules',
+    bulk_delete: 'bulk_delete',
+    bulk_restart: 'bulk_restart',
+    bulk_scale: 'bulk_scale',
+    bulk_label: 'bulk_label',
+    bulk_annotate: 'bulk_annotate',
+    set_labels: 'set_labels',
+    set_annotations: 'set_annotations',
     tail_logs_for_pod: 'tail_logs_for_pod',
+    tail_logs_for_workload: 'tail_logs_for_workload',
+    set_log_filter: 'set_log_filter',
     get_logs_for_pod: 'get_logs_for_pod',
+    get_pod_containers: 'get_pod_containers',
+    export_logs: 'export_logs',
+    search_logs: 'search_logs',
+    list_alert_rules: 'list_alert_rules',
+    save_alert_rule: 'save_alert_rule',
+    delete_alert_rule: 'delete_alert_rule',
     get_environment_variables_for_pod: 'get_environment_variables_for_pod',
     stream_metrics_for_pod: 'stream_metrics_for_pod',
     stream_metrics_for_deployment: 'stream_metrics_for_deployment',
@@ -77,7 +119,14 @@ export class TauriAdapter {
     dashboard_logs: 'dashboard::logs',
     shell_output: 'shell::output',
     app_status_update: 'app::status_update',
-    app_metrics: 'app::metrics'
+    app_metrics: 'app::metrics',
+    app_rollout_status: 'app::rollout_status',
+    app_drain_progress: 'app::drain_progress',
+    app_delete_status: 'app::delete_status',
+    app_protection_required: 'app::protection_required',
+    app_validation_errors: 'app::validation_errors',
+    app_log_batch: 'app::log_batch',
+    app_alert: 'app::alert'
   }
 
   public app_constants = {
@@ -99,6 +148,13 @@ export class TauriAdapter {
       this.response_channel.app_status_update,
       this.response_channel.dashboard_logs,
       this.response_channel.app_metrics,
+      this.response_channel.app_rollout_status,
+      this.response_channel.app_drain_progress,
+      this.response_channel.app_delete_status,
+      this.response_channel.app_protection_required,
+      this.response_channel.app_validation_errors,
+      this.response_channel.app_log_batch,
+      this.response_channel.app_alert,
       this.events.app_events_channel,
       this.events.no_cluster_found,
       this.events.app_error
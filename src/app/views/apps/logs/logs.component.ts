@@ -72,6 +72,7 @@ export class LogsComponent implements EventListener{
 
   ngOnInit() {
     this.data.registerListener(this.data.response_channel.dashboard_logs, this);
+    this.data.registerListener(this.data.response_channel.app_log_batch, this);
     console.log('Get logs');
     this.options = {
       itemChangeCallback: LogsComponent.itemChange,
@@ -195,22 +196,15 @@ export class LogsComponent implements EventListener{
     }
     const event = ev.event;
     const payload = ev.payload;
+    if (payload && ev.name === this.data.response_channel.app_log_batch) {
+      this.handleBatch(payload);
+      return;
+    }
     if (payload) {
       try {
         let log = _.get(payload, 'message');
-        let podname = _.get(payload, 'metadata');
         let original_pod_name = _.get(payload, 'metadata');
-        if (!this.logterminalMap) {
-          this.logterminalMap = new Map<string, TerminalComponent>();
-          this.logterminals?.forEach((tc) => {
-            this.logterminalMap?.set(tc.name, tc);
-          })
-        }
-
-        if(this.mode === 'single') {
-          podname = this.appname;
-        }
-        const terminal = this.logterminalMap.get(podname);
+        const terminal = this.terminalFor(original_pod_name);
         const color = this.getColor(original_pod_name);
         if (terminal) {
           const lines = log.split('\n');
@@ -225,7 +219,7 @@ export class LogsComponent implements EventListener{
             });
           })
         }else{
-          console.log('Terminal not found for: ' + podname);
+          console.log('Terminal not found for: ' + original_pod_name);
         }
       } catch (e) {
         console.error("Failed to parse payload");
@@ -235,6 +229,66 @@ export class LogsComponent implements EventListener{
     }
   }
 
+  terminalFor(podname: string): TerminalComponent | undefined {
+    if (!this.logterminalMap) {
+      this.logterminalMap = new Map<string, TerminalComponent>();
+      this.logterminals?.forEach((tc) => {
+        this.logterminalMap?.set(tc.name, tc);
+      })
+    }
+    return this.logterminalMap.get(this.mode === 'single' ? this.appname : podname);
+  }
+
+  /**
+   * Batched lines carry the include filter matches, the parsed level and the markers the
+   * backend adds when a stream attaches, reconnects or ends, so they are appended directly.
+   */
+  handleBatch(payload: any): void {
+    try {
+      const batch = JSON.parse(_.get(payload, 'message'));
+      const lines = _.get(batch, 'lines', []);
+      lines.forEach((line: any) => {
+        const source = line.pod || batch.stream;
+        const terminal = this.terminalFor(source);
+        if (!terminal) {
+          console.log('Terminal not found for: ' + source);
+          return;
+        }
+        const text: string = line.line || '';
+        if (!line.marker && this.searchTerm && text.indexOf(this.searchTerm) < 0) {
+          return;
+        }
+        terminal.appendContent({
+          source: line.container ? source + '/' + line.container : source,
+          log: text,
+          color: this.getColor(source),
+          segments: LogsComponent.segments(text, line.matches || []),
+          marker: line.marker || undefined,
+          level: _.get(line, 'parsed.level') || undefined
+        });
+      });
+    } catch (e) {
+      console.error("Failed to parse log batch");
+    }
+  }
+
+  /** Splits a line at the matched spans, which are UTF-16 offsets like JS string indexes. */
+  static segments(text: string, matches: number[][]): {text: string, match: boolean}[] {
+    const segments: {text: string, match: boolean}[] = [];
+    let last = 0;
+    matches.forEach(([start, end]) => {
+      if (start > last) {
+        segments.push({text: text.substring(last, start), match: false});
+      }
+      segments.push({text: text.substring(start, end), match: true});
+      last = end;
+    });
+    if (last < text.length) {
+      segments.push({text: text.substring(last), match: false});
+    }
+    return segments;
+  }
+
   liveTail() {
     this.clear();
     this.isLogStreamPaused = false;
@@ -4,6 +4,17 @@ import { FitAddon } from 'xterm-addon-fit';
 import {appWindow} from "@tauri-apps/api/window";
 import * as _ from "lodash";
 
+export interface LogLine {
+  source: string,
+  log: string,
+  color: string,
+  /** The line split at the spans the include filter matched. */
+  segments?: {text: string, match: boolean}[],
+  /** Set on lines the backend adds itself, such as reconnected or ended. */
+  marker?: string,
+  /** The level parsed from a JSON, logfmt or plain line. */
+  level?: string
+}
 
 @Component({
   selector: 'app-terminal',
@@ -23,11 +34,7 @@ export class TerminalComponent implements OnInit {
   viewInitialized = false;
   @Input() isUnicast = true;
   @ViewChild('terminalwindow') terminalwindow: ElementRef | undefined;
-  loglines: {
-    source: string,
-    log: string,
-    color: string
-  }[];
+  loglines: LogLine[];
 
   constructor(private ngZone: NgZone, private el:ElementRef) {
     this.loglines = [];
@@ -36,7 +43,7 @@ export class TerminalComponent implements OnInit {
   ngOnChanges(changes: SimpleChanges): void {
   }
 
-  appendContent(content: {source: string, log: string, color: string}): void {
+  appendContent(content: LogLine): void {
     this.ngZone.run(() => {
       this.loglines.push(content);
       try {